@@ -0,0 +1,550 @@
+use std::{collections::VecDeque, io::{self, Read, Write}, ops::RangeInclusive, path::{Path, PathBuf}, process::{ChildStdout, Stdio}, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering::Relaxed}}, thread, time::Instant};
+
+use cpal::{traits::{DeviceTrait, HostTrait, StreamTrait}, Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use ffmpeg_sidecar::child::FfmpegChild;
+
+use crate::{ffmpeg, video::Video};
+
+/// Queue of interleaved samples, along with how far through them playback has gotten
+#[derive(Default)]
+struct Queue {
+	samples: VecDeque<f32>,
+	/// Number of samples taken off the queue, whether played or skipped, minus the silence inserted into it
+	position: i64,
+	/// Advanced whenever the queue is cleared or a scrub starts, so that slices decoded for an older scrub are dropped
+	generation: u64
+}
+
+/// Interleaved samples waiting to be played by an [`AudioOutput`], which can be shared with other threads
+#[derive(Clone, Default)]
+pub struct Samples(Arc<Mutex<Queue>>);
+
+impl Samples {
+	/// Queues samples to be played after the ones already queued, unless the queue has been cleared or another generation has started since `generation`
+	pub fn push(&self, generation: u64, samples: &[f32]) {
+		let mut queue = self.0.lock().unwrap();
+
+		if queue.generation == generation {
+			queue.samples.extend(samples);
+		}
+	}
+
+	/// Discards all queued samples, along with any slice still being decoded for a scrub
+	pub fn clear(&self) {
+		let mut queue = self.0.lock().unwrap();
+
+		queue.samples.clear();
+		queue.generation += 1;
+	}
+
+	/// Starts a new generation of queued samples, dropping any slice still being decoded for an older one, and returns it
+	pub fn next_generation(&self) -> u64 {
+		let mut queue = self.0.lock().unwrap();
+
+		queue.generation += 1;
+		queue.generation
+	}
+
+	/// Replaces the queued samples, unless the queue has been cleared or another generation has started since `generation`
+	pub fn replace(&self, generation: u64, samples: &[f32]) {
+		let mut queue = self.0.lock().unwrap();
+
+		if queue.generation == generation {
+			queue.samples.clear();
+			queue.samples.extend(samples);
+		}
+	}
+
+	/// Returns how many samples have been played, which only advances while there are samples to play
+	pub fn position(&self) -> i64 {
+		self.0.lock().unwrap().position
+	}
+
+	/// Skips up to `count` queued samples if it's positive, or delays the queued samples by that many samples of silence if it's negative
+	pub fn nudge(&self, count: i64) {
+		let mut queue = self.0.lock().unwrap();
+
+		if count > 0 {
+			let skipped = queue.samples.len().min(count as usize);
+
+			queue.samples.drain(..skipped);
+			queue.position += skipped as i64;
+		} else {
+			for _ in count..0 {
+				queue.samples.push_front(0.0);
+			}
+
+			queue.position += count;
+		}
+	}
+}
+
+/// Plays [`Samples`] on the default output device
+pub struct AudioOutput {
+	pub sample_rate: u32,
+	pub channels: u16,
+	pub samples: Samples,
+	_stream: Stream
+}
+
+impl AudioOutput {
+	/// Opens the default output device, if there is one
+	pub fn new() -> Option<Self> {
+		let device = cpal::default_host().default_output_device()?;
+		let supported = device.default_output_config().ok()?;
+		let config = supported.config();
+
+		let samples = Samples::default();
+
+		let stream = match supported.sample_format() {
+			SampleFormat::I16 => build_stream::<i16>(&device, &config, samples.clone()),
+			SampleFormat::U16 => build_stream::<u16>(&device, &config, samples.clone()),
+			_ => build_stream::<f32>(&device, &config, samples.clone())
+		}?;
+
+		stream.play().ok()?;
+
+		Some(Self {
+			sample_rate: config.sample_rate.0,
+			channels: config.channels,
+			samples,
+			_stream: stream
+		})
+	}
+}
+
+/// Builds an output stream converting [`Samples`] to the device's sample format, playing silence whenever they run out
+fn build_stream<T: SizedSample + FromSample<f32>>(device: &Device, config: &StreamConfig, samples: Samples) -> Option<Stream> {
+	device.build_output_stream(
+		config,
+		move |data: &mut [T], _| {
+			let mut queue = samples.0.lock().unwrap();
+
+			for sample in data {
+				let next = queue.samples.pop_front();
+				queue.position += i64::from(next.is_some());
+
+				*sample = T::from_sample(next.unwrap_or(0.0));
+			}
+
+			drop(queue);
+		},
+		|error| log::error!("Audio output failed: {error}"),
+		None
+	).ok()
+}
+
+/// Returns a chain of `atempo` filters changing the tempo by `speed`, since each one only changes it by a factor between 0.5 and 2
+fn atempo(speed: f32) -> String {
+	// Whole doublings or halvings, leaving a remainder between 0.5 and 2
+	let steps = speed.log2().trunc() as i32;
+	let factor = if steps > 0 { "atempo=2" } else { "atempo=0.5" };
+
+	let mut filters = vec![String::from(factor); steps.unsigned_abs() as usize];
+	filters.push(format!("atempo={}", speed / 2_f32.powi(steps)));
+
+	filters.join(",")
+}
+
+/// Starts `FFmpeg` decoding a file's audio into interleaved `f32` samples matching an [`AudioOutput`] on it's stdout
+///
+/// `speed` changes how fast the audio plays, either keeping it's pitch or shifting the pitch along with the speed like a tape
+///
+/// Without a `length`, the rest of the file is decoded in real time to stay in sync with playback
+pub fn spawn_decoder(path: &Path, start: f32, length: Option<f32>, output: (u32, u16), speed: f32, preserve_pitch: bool) -> Option<FfmpegChild> {
+	let (sample_rate, channels) = output;
+	// Far beyond any playback speed, but keeps the filter chains short
+	let speed = speed.clamp(0.01, 100.0);
+
+	let mut command = ffmpeg::command();
+
+	command
+		.hide_banner()
+		.create_no_window()
+		.seek(start.to_string());
+
+	if let Some(length) = length {
+		command.duration((length * speed).to_string());
+	} else {
+		command.readrate(speed);
+	}
+
+	command
+		.input(path.to_string_lossy())
+		.no_video()
+		.args(["-sn", "-dn"]);
+
+	if (speed - 1.0).abs() > 0.01 {
+		command.filter(if preserve_pitch {
+			atempo(speed)
+		} else {
+			format!("asetrate={},aresample={sample_rate}", sample_rate as f32 * speed)
+		});
+	}
+
+	command
+		.args(["-ac", &channels.to_string(), "-ar", &sample_rate.to_string()])
+		.format("f32le")
+		.pipe_stdout()
+		.as_inner_mut().stderr(Stdio::null()); // Nothing reads the log
+
+	command.spawn().ok()
+}
+
+/// Converts little endian bytes from `FFmpeg` into samples
+pub fn to_samples(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+	bytes.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes part of a file's audio into interleaved samples matching an [`AudioOutput`]
+pub fn decode(path: &Path, start: f32, length: f32, output: (u32, u16), speed: f32, preserve_pitch: bool) -> Vec<f32> {
+	let Some(mut ffmpeg) = spawn_decoder(path, start, Some(length), output, speed, preserve_pitch) else { return Vec::new() };
+
+	let mut bytes = Vec::new();
+
+	if let Some(mut stdout) = ffmpeg.take_stdout() {
+		drop(stdout.read_to_end(&mut bytes));
+	}
+
+	drop(ffmpeg.wait());
+
+	to_samples(&bytes).collect()
+}
+
+/// Returns whether a [`Video`] is heard at all, given whether any [`Video`] is soloed
+///
+/// Muted [`Video`]s are never heard, and once any [`Video`] is soloed, only soloed ones are
+fn is_heard(video: &Video, solo: bool) -> bool {
+	video.source.has_audio &&
+	video.error.is_none() &&
+	!video.muted &&
+	(!solo || video.solo)
+}
+
+/// Returns the [`Video`]s which should be heard at the playhead
+fn audible(videos: &[Video], playhead: f32) -> impl Iterator<Item = &Video> {
+	let solo = videos.iter().any(|video| video.solo);
+
+	videos.iter().filter(move |video| is_heard(video, solo) && video.is_active(playhead))
+}
+
+/// Audio of a [`Video`] heard in a [`Mix`], from the `start`th to the `end`th sample of each channel
+struct MixClip {
+	path: PathBuf,
+	/// Time in the source the audio is heard from
+	source_start: f32,
+	speed: f32,
+	volume: f32,
+	start: usize,
+	end: usize
+}
+
+/// Audio of every [`Video`] heard in part of the timeline, which is decoded and mixed a chunk at a time as it's written, for exports
+pub struct Mix {
+	clips: Vec<MixClip>,
+	/// Length of the mix in samples per channel
+	frames: usize,
+	output: (u32, u16)
+}
+
+/// Prepares the [`Mix`] of every [`Video`] heard within `range` of the timeline, without decoding anything yet
+///
+/// Returns `None` if nothing is heard in the range at all
+pub fn mix(videos: &[Video], range: &RangeInclusive<f32>, output: (u32, u16)) -> Option<Mix> {
+	let solo = videos.iter().any(|video| video.solo);
+	let frame_at = |time: f32| ((time - range.start()) * output.0 as f32).round() as usize;
+
+	let clips: Vec<_> = videos.iter()
+		.filter(|video| is_heard(video, solo))
+		.filter_map(|video| {
+			let start = video.duration.start().max(*range.start());
+			let end = video.end().min(*range.end());
+
+			(end > start).then(|| MixClip {
+				path: video.source.path.clone(),
+				source_start: video.source_time(start),
+				speed: video.speed(),
+				volume: video.volume,
+				start: frame_at(start),
+				end: frame_at(end)
+			})
+		})
+		.collect();
+
+	(!clips.is_empty()).then(|| Mix {
+		clips,
+		frames: frame_at(*range.end()),
+		output
+	})
+}
+
+impl Mix {
+	/// Decodes and mixes the audio, writing it as interleaved little endian `f32` samples a chunk at a time, so that only a chunk is held at once
+	///
+	/// Clips start decoding once they're first heard and quit once they end, and the mix stops early once `stop` is set
+	pub fn write(self, out: &mut impl Write, stop: &AtomicBool) -> io::Result<()> {
+		let (sample_rate, channels) = self.output;
+		let channels = usize::from(channels);
+
+		let mut decoders: Vec<Option<(FfmpegChild, ChildStdout)>> = self.clips.iter().map(|_| None).collect();
+		let mut mix = vec![0.0; CHUNK_FRAMES * channels];
+		let mut bytes = Vec::new();
+		let mut frame = 0;
+
+		while frame < self.frames && !stop.load(Relaxed) {
+			let len = CHUNK_FRAMES.min(self.frames - frame);
+			let chunk = &mut mix[..len * channels];
+			chunk.fill(0.0);
+
+			for (clip, decoder) in self.clips.iter().zip(&mut decoders) {
+				let start = clip.start.max(frame);
+				let end = clip.end.min(frame + len);
+
+				if start >= end {
+					continue;
+				}
+
+				if decoder.is_none() {
+					let length = (clip.end - clip.start) as f32 / sample_rate as f32;
+
+					*decoder = spawn_decoder(&clip.path, clip.source_start, Some(length), self.output, clip.speed, true).and_then(|mut ffmpeg| {
+						let stdout = ffmpeg.take_stdout()?;
+						Some((ffmpeg, stdout))
+					});
+				}
+
+				if let Some((_, stdout)) = decoder {
+					bytes.resize((end - start) * channels * 4, 0);
+
+					// Clips which run out of audio early are silent for the rest of their length
+					if stdout.read_exact(&mut bytes).is_ok() {
+						for (mixed, sample) in chunk[(start - frame) * channels..].iter_mut().zip(to_samples(&bytes)) {
+							*mixed = sample.mul_add(clip.volume, *mixed).clamp(-1.0, 1.0);
+						}
+					}
+				}
+
+				if clip.end <= frame + len {
+					if let Some((mut ffmpeg, _)) = decoder.take() {
+						drop(ffmpeg.kill());
+						drop(ffmpeg.wait());
+					}
+				}
+			}
+
+			bytes.clear();
+			bytes.extend(chunk.iter().flat_map(|sample| sample.to_le_bytes()));
+			out.write_all(&bytes)?;
+
+			frame += len;
+		}
+
+		for (mut ffmpeg, _) in decoders.into_iter().flatten() {
+			drop(ffmpeg.kill());
+			drop(ffmpeg.wait());
+		}
+
+		Ok(())
+	}
+}
+
+/// Loudest a [`Video`]'s volume can be turned up to
+pub const MAX_VOLUME: f32 = 4.0;
+
+/// Number of samples per channel which [`Playback`] mixes at a time
+const CHUNK_FRAMES: usize = 1024;
+
+/// Seconds the audio may run ahead of or behind the playhead before it's nudged back in sync
+const MAX_DRIFT: f32 = 0.04;
+
+/// Plays the mixed audio of every [`Video`] under the playhead, following it while the project is playing
+#[derive(Default)]
+pub struct Playback {
+	/// IDs and volumes of the [`Video`]s currently being played, so playback restarts when clips start or end or are turned up or down
+	clips: Vec<(u64, f32)>,
+	speed: f32,
+	stop: Option<Arc<AtomicBool>>,
+	/// Playhead and [`Samples`] position playback started at, from which the time of the audio being heard is counted
+	clock: Option<(f32, i64)>,
+	/// Seconds the audio was ahead of the playhead when last checked, or behind it if negative
+	pub drift: f32
+}
+
+impl Playback {
+	/// Keeps playback in sync with the playhead, restarting it if the set of clips under the playhead has changed
+	///
+	/// Should be called every frame while playing
+	pub fn update(&mut self, output: &AudioOutput, videos: &[Video], playhead: f32, speed: f32) {
+		let clips: Vec<_> = audible(videos, playhead).map(|video| (video.id, video.volume)).collect();
+
+		if self.stop.is_none() || clips != self.clips || (speed - self.speed).abs() > f32::EPSILON {
+			self.start(output, videos, playhead, speed);
+		}
+
+		self.sync(output, playhead);
+	}
+
+	/// Measures how far the audio has drifted from the playhead, skipping queued audio to catch up or inserting silence to wait if that's more than [`MAX_DRIFT`]
+	///
+	/// The audio and the playhead advance on separate clocks, so without this the small differences between them add up over long clips
+	fn sync(&mut self, output: &AudioOutput, playhead: f32) {
+		let Some((start, position)) = self.clock else {
+			self.drift = 0.0;
+			return;
+		};
+
+		let channels = i64::from(output.channels);
+		let played = (output.samples.position() - position) / channels;
+
+		self.drift = (played as f32 / output.sample_rate as f32).mul_add(self.speed, start) - playhead;
+
+		if self.drift.abs() > MAX_DRIFT {
+			// Whole frames of samples, so that channels don't get swapped
+			let frames = (-self.drift / self.speed * output.sample_rate as f32) as i64;
+			output.samples.nudge(frames * channels);
+		}
+	}
+
+	/// Starts playing from the playhead at a playback speed, replacing anything already playing
+	///
+	/// Audio can't be played in reverse, so negative speeds are silent
+	pub fn start(&mut self, output: &AudioOutput, videos: &[Video], playhead: f32, speed: f32) {
+		self.stop(output);
+
+		self.speed = speed;
+
+		let format = (output.sample_rate, output.channels);
+		let mut decoders = Vec::new();
+
+		self.clips.clear();
+
+		for video in audible(videos, playhead) {
+			self.clips.push((video.id, video.volume));
+
+			if speed <= 0.0 {
+				continue;
+			}
+
+			if let Some(decoder) = spawn_decoder(&video.source.path, video.source_time(playhead), None, format, video.speed() * speed, true) {
+				decoders.push((decoder, video.volume));
+			}
+		}
+
+		let stop = Arc::new(AtomicBool::new(false));
+		self.stop = Some(stop.clone());
+
+		if decoders.is_empty() {
+			self.clock = None;
+			return;
+		}
+
+		self.clock = Some((playhead, output.samples.position()));
+
+		let samples = output.samples.clone();
+		let generation = samples.next_generation();
+		let chunk_len = CHUNK_FRAMES * format.1 as usize * 4;
+
+		thread::spawn(move || {
+			let mut stdouts: Vec<_> = decoders.iter_mut().filter_map(|(decoder, volume)| Some((decoder.take_stdout()?, *volume))).collect();
+			let mut bytes = vec![0; chunk_len];
+			let mut mix = vec![0.0; chunk_len / 4];
+
+			while !stop.load(Relaxed) && !stdouts.is_empty() {
+				mix.fill(0.0);
+
+				// Decoders which have run out of audio are removed
+				stdouts.retain_mut(|(stdout, volume)| {
+					let ok = stdout.read_exact(&mut bytes).is_ok();
+
+					if ok {
+						for (mixed, sample) in mix.iter_mut().zip(to_samples(&bytes)) {
+							*mixed = sample.mul_add(*volume, *mixed).clamp(-1.0, 1.0);
+						}
+					}
+
+					ok
+				});
+
+				// Playback may have been stopped while the decoders were being read, after the queue was cleared
+				if stop.load(Relaxed) {
+					break;
+				}
+
+				samples.push(generation, &mix);
+			}
+
+			for (mut decoder, _) in decoders {
+				drop(decoder.kill());
+				drop(decoder.wait());
+			}
+		});
+	}
+
+	/// Stops playback and discards any queued audio
+	pub fn stop(&mut self, output: &AudioOutput) {
+		if let Some(stop) = self.stop.take() {
+			stop.store(true, Relaxed);
+		}
+
+		self.clock = None;
+
+		output.samples.clear();
+	}
+}
+
+/// Length in seconds of the slice of audio played each time the playhead is scrubbed
+const SCRUB_LENGTH: f32 = 0.15;
+
+/// Plays short slices of the mixed audio at the playhead while scrubbing, to help find dialogue and beats
+#[derive(Default)]
+pub struct AudioScrub {
+	pub enabled: bool,
+	/// Whether slices scrubbed faster or slower than real time keep their pitch
+	pub preserve_pitch: bool,
+	last: Option<(f32, Instant)>
+}
+
+impl AudioScrub {
+	/// Plays the audio at the playhead, sped up or slowed down to match how fast it's being scrubbed
+	pub fn scrub(&mut self, output: &AudioOutput, videos: &[Video], playhead: f32) {
+		if !self.enabled {
+			return;
+		}
+
+		let now = Instant::now();
+
+		let scrub_speed = self.last.map_or(1.0, |(last_playhead, last_time)| {
+			(playhead - last_playhead).abs() / now.duration_since(last_time).as_secs_f32().max(SCRUB_LENGTH)
+		});
+
+		self.last = Some((playhead, now));
+
+		let clips: Vec<(PathBuf, f32, f32, f32)> = audible(videos, playhead)
+			.map(|video| (video.source.path.clone(), video.source_time(playhead), scrub_speed * video.speed(), video.volume))
+			.collect();
+
+		let format = (output.sample_rate, output.channels);
+		let preserve_pitch = self.preserve_pitch;
+		let samples = output.samples.clone();
+		let generation = samples.next_generation();
+
+		thread::spawn(move || {
+			let mut mix: Vec<f32> = Vec::new();
+
+			for (path, start, speed, volume) in clips {
+				let slice = decode(&path, start, SCRUB_LENGTH, format, speed, preserve_pitch);
+
+				if slice.len() > mix.len() {
+					mix.resize(slice.len(), 0.0);
+				}
+
+				for (mixed, sample) in mix.iter_mut().zip(slice) {
+					*mixed = sample.mul_add(volume, *mixed).clamp(-1.0, 1.0);
+				}
+			}
+
+			// Only the latest scrub position is heard, and none once playback has cleared the queue
+			samples.replace(generation, &mix);
+		});
+	}
+}
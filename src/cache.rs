@@ -0,0 +1,110 @@
+use std::{collections::{HashMap, VecDeque}, sync::Arc};
+
+use tiny_skia::Pixmap;
+
+/// Number of lookups between each debug log of the hit rate
+const REPORT_INTERVAL: u32 = 256;
+
+/// Recently shown frames of a [`Video`](crate::video::Video), keyed by frame number, so that scrubbing back over them doesn't require seeking `FFmpeg`
+///
+/// The least recently used frames are evicted once the frames take up more than `capacity` bytes
+pub struct FrameCache {
+	/// Frames along with when they were last used
+	frames: HashMap<u32, (u64, Arc<Pixmap>)>,
+	/// Frame numbers ordered from least to most recently used, along with when they were used
+	///
+	/// Frames are pushed again each time they're used, leaving older uses behind which are skipped once they don't match the frame's last use
+	order: VecDeque<(u64, u32)>,
+	/// Number of uses so far, which orders them
+	uses: u64,
+	bytes: usize,
+	capacity: usize,
+	hits: u32,
+	lookups: u32
+}
+
+impl FrameCache {
+	/// Creates an empty [`FrameCache`] holding at most `capacity` bytes of frames
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			frames: HashMap::new(),
+			order: VecDeque::new(),
+			uses: 0,
+			bytes: 0,
+			capacity,
+			hits: 0,
+			lookups: 0
+		}
+	}
+
+	/// Marks a cached frame as the most recently used one
+	fn touch(&mut self, num: u32) {
+		self.uses += 1;
+		self.order.push_back((self.uses, num));
+
+		// Older uses are dropped once they outnumber the frames, so that the order doesn't grow while the same frames are used over and over
+		if self.order.len() > self.frames.len() * 2 {
+			let frames = &self.frames;
+			self.order.retain(|&(used, num)| frames.get(&num).is_some_and(|&(last, _)| last == used));
+		}
+	}
+
+	/// Returns a cached frame, marking it as recently used
+	pub fn get(&mut self, num: u32) -> Option<Arc<Pixmap>> {
+		let uses = self.uses + 1;
+
+		let frame = self.frames.get_mut(&num).map(|(used, frame)| {
+			*used = uses;
+			frame.clone()
+		});
+
+		self.lookups += 1;
+
+		if frame.is_some() {
+			self.touch(num);
+			self.hits += 1;
+		}
+
+		// Logged so that the capacity can be tuned
+		if self.lookups % REPORT_INTERVAL == 0 {
+			log::debug!("Frame cache hit rate: {:.1}% of {} lookups", self.hits as f32 / self.lookups as f32 * 100.0, self.lookups);
+		}
+
+		frame
+	}
+
+	/// Adds a frame to the cache, evicting the least recently used ones if it's full
+	pub fn insert(&mut self, num: u32, frame: Arc<Pixmap>) {
+		let size = frame.data().len();
+
+		if size > self.capacity {
+			return;
+		}
+
+		if let Some((_, replaced)) = self.frames.remove(&num) {
+			self.bytes -= replaced.data().len();
+		}
+
+		while self.bytes + size > self.capacity {
+			let Some((used, evicted)) = self.order.pop_front() else { break };
+
+			// Skips uses of frames which have been used again since
+			if self.frames.get(&evicted).is_some_and(|&(last, _)| last == used) {
+				if let Some((_, evicted)) = self.frames.remove(&evicted) {
+					self.bytes -= evicted.data().len();
+				}
+			}
+		}
+
+		self.bytes += size;
+		self.frames.insert(num, (self.uses + 1, frame));
+		self.touch(num);
+	}
+
+	/// Removes every frame, for when frames are decoded differently from then on
+	pub fn clear(&mut self) {
+		self.frames.clear();
+		self.order.clear();
+		self.bytes = 0;
+	}
+}
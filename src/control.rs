@@ -0,0 +1,139 @@
+use std::{io::{self, BufRead, BufReader, ErrorKind, Write}, net::{Ipv4Addr, TcpListener, TcpStream}, sync::{mpsc::{channel, Receiver}, Arc, Mutex}, thread};
+
+use serde::{Deserialize, Serialize};
+
+/// Port the control server listens on when `--control` isn't given one
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Most bytes that may be waiting to be sent to a controller before it's considered to have stopped keeping up
+const MAX_UNSENT: usize = 4096;
+
+/// Command sent by an external controller, as a line of JSON such as `{"command": "seek", "time": 12.5}`
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Command {
+	Play,
+	Pause,
+	/// Moves the playhead to a time in seconds
+	Seek { time: f32 },
+	/// Sets the playback speed, where negative speeds play in reverse
+	Speed { speed: f32 }
+}
+
+impl Command {
+	/// Parses a line of JSON into a [`Command`], if it's a known one with valid arguments
+	fn parse(line: &str) -> Option<Self> {
+		serde_json::from_str(line).ok().filter(|command| match *command {
+			Self::Play | Self::Pause => true,
+			Self::Seek { time: number } | Self::Speed { speed: number } => number.is_finite()
+		})
+	}
+}
+
+/// Playback state sent to controllers, as a line of JSON such as `{"playhead": 12.5, "playing": true, "speed": 1.0}`
+#[derive(Serialize)]
+struct State {
+	playhead: f32,
+	playing: bool,
+	speed: f32
+}
+
+/// Connected controller, along with whatever part of the state couldn't be sent to it yet
+struct Client {
+	stream: TcpStream,
+	unsent: Vec<u8>
+}
+
+impl Client {
+	/// Sends as much of the unsent state as the controller will take without waiting, returning whether it's still keeping up
+	fn flush(&mut self) -> bool {
+		while !self.unsent.is_empty() {
+			match self.stream.write(&self.unsent) {
+				Ok(0) => return false,
+				Ok(written) => drop(self.unsent.drain(..written)),
+				Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+				Err(error) if error.kind() == ErrorKind::Interrupted => {},
+				Err(_) => return false
+			}
+		}
+
+		self.unsent.len() <= MAX_UNSENT
+	}
+}
+
+/// Localhost TCP server through which external controllers, such as jog wheels or scripts, drive playback
+///
+/// Controllers send [`Command`]s and are sent the playback state whenever it changes, one JSON object per line
+pub struct ControlServer {
+	commands: Receiver<Command>,
+	/// Connected controllers, which are disconnected once they stop keeping up with the state sent to them
+	clients: Arc<Mutex<Vec<Client>>>,
+	/// Last state sent, so that it's only sent again once it changes
+	last: String
+}
+
+impl ControlServer {
+	/// Starts listening on a port of localhost, accepting controllers in the background
+	pub fn start(port: u16) -> io::Result<Self> {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+		let (sender, commands) = channel();
+		let clients: Arc<Mutex<Vec<Client>>> = Arc::default();
+		let accepted = clients.clone();
+
+		thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				// Writes never wait for the controller, so that a slow one can't stall the window
+				let Ok(writer) = stream.try_clone().and_then(|writer| writer.set_nonblocking(true).map(|()| writer)) else { continue };
+
+				accepted.lock().unwrap().push(Client {
+					stream: writer,
+					unsent: Vec::new()
+				});
+
+				let sender = sender.clone();
+
+				thread::spawn(move || {
+					for line in BufReader::new(stream).lines().map_while(Result::ok) {
+						if let Some(command) = Command::parse(&line) {
+							if sender.send(command).is_err() {
+								break; // The window was closed
+							}
+						} else {
+							log::warn!("Unknown control command: {line}");
+						}
+					}
+				});
+			}
+		});
+
+		Ok(Self {
+			commands,
+			clients,
+			last: String::new()
+		})
+	}
+
+	/// Returns the next [`Command`] received from any controller, if there is one
+	pub fn poll(&self) -> Option<Command> {
+		self.commands.try_recv().ok()
+	}
+
+	/// Sends the playback state to every connected controller, unless it's the same as the last one sent, and carries on sending any that they weren't ready for
+	///
+	/// Only whole lines are queued, so a controller that's sent part of one gets the rest of it before anything else
+	pub fn broadcast(&mut self, playhead: f32, playing: bool, speed: f32) {
+		let Ok(state) = serde_json::to_string(&State { playhead, playing, speed }) else { return };
+		let mut clients = self.clients.lock().unwrap();
+
+		if state != self.last {
+			for client in clients.iter_mut() {
+				client.unsent.extend_from_slice(state.as_bytes());
+				client.unsent.push(b'\n');
+			}
+
+			self.last = state;
+		}
+
+		clients.retain_mut(Client::flush);
+	}
+}
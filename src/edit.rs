@@ -0,0 +1,341 @@
+use std::path::PathBuf;
+
+use crate::{source::Sources, video::{Video, VideoState}};
+
+/// Number of edits kept in the [`History`] unless configured otherwise
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// A reversible edit made by the user, stored in the [`History`]
+pub enum EditCommand {
+	/// A single [`Video`] was added to the project
+	Add {
+		id: u64,
+		path: PathBuf,
+		start: f32,
+		track: u8,
+		slow_motion: bool
+	},
+	/// A [`Video`] was added to the project at `index` in the list of [`Video`]s with settings taken from another, such as when one is split in two
+	Insert {
+		id: u64,
+		index: usize,
+		state: VideoState
+	},
+	/// Several edits were made at once, and are undone and redone as a single step
+	Batch(Vec<Self>),
+	/// A [`Video`] was moved, resized or had any of it's other settings changed
+	Change {
+		id: u64,
+		before: VideoState,
+		after: VideoState
+	},
+	/// A [`Video`] was removed from the project, from `index` in the list of [`Video`]s
+	Remove {
+		id: u64,
+		index: usize,
+		state: VideoState
+	}
+}
+
+impl EditCommand {
+	/// Creates an [`EditCommand::Add`] describing an already added [`Video`]
+	pub fn add(video: &Video) -> Self {
+		Self::Add {
+			id: video.id,
+			path: video.source.path.clone(),
+			start: *video.duration.start(),
+			track: video.track,
+			slow_motion: video.slow_motion
+		}
+	}
+
+	/// Creates an [`EditCommand::Remove`] describing a [`Video`] before it's removed
+	pub fn remove(video: &Video, index: usize) -> Self {
+		Self::Remove {
+			id: video.id,
+			index,
+			state: video.state()
+		}
+	}
+
+	/// Reverts the edit
+	fn undo(&self, videos: &mut Vec<Video>, sources: &mut Sources) {
+		match self {
+			Self::Add { id, .. } | Self::Insert { id, .. } => remove(videos, *id),
+			Self::Batch(commands) => for command in commands.iter().rev() {
+				command.undo(videos, sources);
+			},
+			Self::Change { id, before, .. } => if let Some(video) = videos.iter_mut().find(|video| video.id == *id) {
+				video.apply(before);
+			},
+			Self::Remove { id, index, state } => if let Ok(mut video) = Video::from_state(state, sources) {
+				video.id = *id;
+				videos.insert((*index).min(videos.len()), video);
+			}
+		}
+	}
+
+	/// Reapplies the edit after it has been undone
+	///
+	/// Recreated [`Video`]s keep their old IDs, so that edits further along the [`History`] still find them
+	fn redo(&self, videos: &mut Vec<Video>, sources: &mut Sources) {
+		match self {
+			Self::Add { id, path, start, track, slow_motion } => if let Ok(mut video) = Video::new(path.clone(), *start, sources) {
+				video.track = *track;
+				video.slow_motion = *slow_motion;
+				video.id = *id;
+				videos.push(video);
+			},
+			Self::Insert { id, index, state } => if let Ok(mut video) = Video::from_state(state, sources) {
+				video.id = *id;
+				videos.insert((*index).min(videos.len()), video);
+			},
+			Self::Batch(commands) => for command in commands {
+				command.redo(videos, sources);
+			},
+			Self::Change { id, after, .. } => if let Some(video) = videos.iter_mut().find(|video| video.id == *id) {
+				video.apply(after);
+			},
+			Self::Remove { id, .. } => remove(videos, *id)
+		}
+	}
+}
+
+/// Removes the [`Video`] at `index` and moves every later one on the same track back by it's length, closing the gap it leaves
+///
+/// Returns the edit as an [`EditCommand::Batch`], so that the moves are undone along with the removal
+pub fn ripple_remove(videos: &mut Vec<Video>, index: usize) -> EditCommand {
+	let mut commands = vec![EditCommand::remove(&videos[index], index)];
+
+	// `FFmpeg` is quit before the video is dropped so that it doesn't linger
+	let mut removed = videos.remove(index);
+	drop(removed.ffmpeg.quit());
+
+	let end = removed.end();
+	let length = end - removed.duration.start();
+
+	for video in videos.iter_mut().filter(|video| video.track == removed.track && *video.duration.start() >= end) {
+		let before = video.state();
+		video.move_to(video.duration.start() - length);
+
+		commands.push(EditCommand::Change { id: video.id, before, after: video.state() });
+	}
+
+	EditCommand::Batch(commands)
+}
+
+/// Splits the [`Video`] at `index` in two at a timestamp, adding the second part right after it
+///
+/// Returns the edit as an [`EditCommand::Batch`], unless the timestamp is outside of the [`Video`]
+pub fn split(videos: &mut Vec<Video>, index: usize, timestamp: f32, sources: &mut Sources) -> Option<EditCommand> {
+	let before = videos[index].state();
+	let rest = videos[index].split(timestamp)?;
+
+	let second = match Video::from_state(&rest, sources) {
+		Ok(second) => second,
+		Err(error) => {
+			log::error!("Splitting {} failed: {error}", rest.path.display());
+
+			videos[index].apply(&before); // Nothing was split after all
+			return None;
+		}
+	};
+
+	let change = EditCommand::Change { id: videos[index].id, before, after: videos[index].state() };
+	let insert = EditCommand::Insert { id: second.id, index: index + 1, state: rest };
+
+	videos.insert(index + 1, second);
+
+	Some(EditCommand::Batch(vec![change, insert]))
+}
+
+/// Adds copies of the [`Video`]s described by `clipboard` on top of the rest, moved together so that the earliest one starts at `start`
+///
+/// Returns an [`EditCommand::Insert`] for each copy, leaving out any whose source can no longer be loaded
+pub fn paste(videos: &mut Vec<Video>, clipboard: &[VideoState], start: f32, sources: &mut Sources) -> Vec<EditCommand> {
+	let earliest = clipboard.iter().map(|state| state.start).fold(f32::INFINITY, f32::min);
+
+	clipboard.iter()
+		.filter_map(|state| match Video::from_state(state, sources) {
+			Ok(mut video) => {
+				// Copies keep their distance to each other
+				video.move_to(start + state.start - earliest);
+
+				let insert = EditCommand::Insert { id: video.id, index: videos.len(), state: video.state() };
+				videos.push(video);
+
+				Some(insert)
+			},
+			Err(error) => {
+				log::error!("Pasting {} failed: {error}", state.path.display());
+				None
+			}
+		})
+		.collect()
+}
+
+/// Removes a [`Video`] by ID, quitting it's `FFmpeg` instance
+fn remove(videos: &mut Vec<Video>, id: u64) {
+	videos.retain_mut(|video| {
+		let keep = video.id != id;
+
+		if !keep {
+			drop(video.ffmpeg.quit());
+		}
+
+		keep
+	});
+}
+
+/// Undo and redo stacks of [`EditCommand`]s
+pub struct History {
+	undo: Vec<EditCommand>,
+	redo: Vec<EditCommand>,
+	/// Maximum number of edits which can be undone, beyond which the oldest ones are forgotten
+	limit: usize,
+	/// Number of edits that could be undone when the project was last saved, or `None` if that point can't be returned to
+	saved: Option<usize>
+}
+
+impl Default for History {
+	fn default() -> Self {
+		Self::new(DEFAULT_LIMIT)
+	}
+}
+
+impl History {
+	/// Creates an empty [`History`] keeping at most `limit` edits
+	pub const fn new(limit: usize) -> Self {
+		Self {
+			undo: Vec::new(),
+			redo: Vec::new(),
+			limit,
+			saved: Some(0)
+		}
+	}
+
+	/// Records a new edit, which clears everything that could be redone
+	pub fn push(&mut self, command: EditCommand) {
+		// Saving after undoing leaves the saved state in the cleared redo stack
+		self.saved = self.saved.filter(|&saved| saved <= self.undo.len());
+
+		self.undo.push(command);
+		self.redo.clear();
+
+		if self.undo.len() > self.limit {
+			let forgotten = self.undo.len() - self.limit;
+
+			self.undo.drain(..forgotten);
+			self.saved = self.saved.and_then(|saved| saved.checked_sub(forgotten));
+		}
+	}
+
+	/// Marks the current state as saved, so that the project counts as unchanged until it's edited or edits are undone
+	pub fn mark_saved(&mut self) {
+		self.saved = Some(self.undo.len());
+	}
+
+	/// Returns whether the project has changed since it was last saved or opened
+	pub fn is_dirty(&self) -> bool {
+		self.saved != Some(self.undo.len())
+	}
+
+	/// Records several edits made at once as a single [`EditCommand::Batch`], or on their own if there's only one
+	pub fn push_all(&mut self, mut commands: Vec<EditCommand>) {
+		if commands.len() > 1 {
+			self.push(EditCommand::Batch(commands));
+		} else if let Some(command) = commands.pop() {
+			self.push(command);
+		}
+	}
+
+	/// Records an [`EditCommand::Change`] between two states of a [`Video`], unless nothing changed
+	pub fn change(&mut self, id: u64, before: VideoState, after: VideoState) {
+		if before != after {
+			self.push(EditCommand::Change { id, before, after });
+		}
+	}
+
+	/// Edits a [`Video`] and records the change
+	pub fn edit(&mut self, video: &mut Video, edit: impl FnOnce(&mut Video)) {
+		let before = video.state();
+		edit(video);
+		self.change(video.id, before, video.state());
+	}
+
+	/// Edits several [`Video`]s the same way, recording the changes as a single step
+	pub fn edit_all<'a>(&mut self, videos: impl IntoIterator<Item = &'a mut Video>, edit: impl Fn(&mut Video)) {
+		let changes = videos.into_iter()
+			.filter_map(|video| {
+				let before = video.state();
+				edit(video);
+				let after = video.state();
+
+				(before != after).then_some(EditCommand::Change { id: video.id, before, after })
+			})
+			.collect();
+
+		self.push_all(changes);
+	}
+
+	/// Reverts the last edit, if any
+	pub fn undo(&mut self, videos: &mut Vec<Video>, sources: &mut Sources) {
+		if let Some(command) = self.undo.pop() {
+			command.undo(videos, sources);
+			self.redo.push(command);
+		}
+	}
+
+	/// Reapplies the last undone edit, if any
+	pub fn redo(&mut self, videos: &mut Vec<Video>, sources: &mut Sources) {
+		if let Some(command) = self.redo.pop() {
+			command.redo(videos, sources);
+			self.undo.push(command);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::video::tests::test_clip;
+
+	#[test]
+	fn ripple_remove_closes_the_gap_on_the_same_track() {
+		let Some(path) = test_clip("ripple", 1, 10) else { return };
+		let mut sources = Sources::default();
+
+		// Three adjacent clips on the first track, and one on the second which starts after the middle one ends
+		let mut videos: Vec<_> = [(0.0, 0), (1.0, 0), (2.0, 0), (2.5, 1)].into_iter().enumerate().map(|(i, (start, track))| {
+			let Ok(mut video) = Video::new(path.clone(), start, &mut sources) else { panic!("Couldn't open {}", path.display()) };
+			video.track = track;
+			video.id = i as u64;
+			video
+		}).collect();
+
+		let length = videos[1].end() - videos[1].duration.start();
+		let mut history = History::default();
+		history.push(ripple_remove(&mut videos, 1));
+
+		let start_of = |videos: &[Video], id: u64| videos.iter().find(|video| video.id == id).map(|video| *video.duration.start());
+
+		assert_eq!(videos.len(), 3);
+		assert_eq!(start_of(&videos, 1), None);
+		assert!((start_of(&videos, 2).unwrap() - (2.0 - length)).abs() < 1e-4, "The third clip moves left by the removed length");
+		assert_eq!(start_of(&videos, 0), Some(0.0));
+		assert_eq!(start_of(&videos, 3), Some(2.5), "Clips on other tracks don't move");
+
+		history.undo(&mut videos, &mut sources);
+
+		assert_eq!(videos.len(), 4);
+		assert_eq!(start_of(&videos, 1), Some(1.0));
+		assert_eq!(start_of(&videos, 2), Some(2.0));
+		assert_eq!(start_of(&videos, 3), Some(2.5));
+
+		for video in &mut videos {
+			drop(video.ffmpeg.quit());
+		}
+
+		drop(std::fs::remove_file(path));
+	}
+}
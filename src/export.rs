@@ -0,0 +1,783 @@
+use std::{ffi::OsStr, fs, io::{self, Write}, ops::RangeInclusive, path::{Path, PathBuf}, process::ChildStdin, sync::{Arc, atomic::{AtomicBool, Ordering::Relaxed}}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use ffmpeg_sidecar::child::FfmpegChild;
+use tiny_skia::{Color, Pixmap, Transform};
+
+use crate::{audio, ffmpeg, render::{render_frame, ScaleQuality}, video::{DecodeQuality, Video}};
+
+/// Corner of the frame in which an overlay is drawn
+#[derive(Clone, Copy)]
+pub enum Corner {
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	BottomRight
+}
+
+impl Corner {
+	/// Returns the next [`Corner`] clockwise, for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::TopLeft => Self::TopRight,
+			Self::TopRight => Self::BottomRight,
+			Self::BottomRight => Self::BottomLeft,
+			Self::BottomLeft => Self::TopLeft
+		}
+	}
+
+	/// Returns `drawtext` expressions for the position of text in this [`Corner`], with a margin from the edges
+	const fn position(self) -> (&'static str, &'static str) {
+		match self {
+			Self::TopLeft => ("16", "16"),
+			Self::TopRight => ("w-tw-16", "16"),
+			Self::BottomLeft => ("16", "h-th-16"),
+			Self::BottomRight => ("w-tw-16", "h-th-16")
+		}
+	}
+}
+
+/// Timecode burned into every exported frame, for review copies
+pub struct TimecodeOverlay {
+	pub corner: Corner,
+	pub font_size: u32,
+	/// Any color `FFmpeg` understands, such as `white` or `#FF8604`
+	pub color: String,
+	/// Whether the frame number is drawn below the timecode
+	pub frame_number: bool
+}
+
+impl Default for TimecodeOverlay {
+	fn default() -> Self {
+		Self {
+			corner: Corner::BottomRight,
+			font_size: 32,
+			color: String::from("white"),
+			frame_number: false
+		}
+	}
+}
+
+impl TimecodeOverlay {
+	/// Creates an `FFmpeg` filtergraph drawing the timecode at a specific frame rate
+	fn filter(&self, fps: f32) -> String {
+		let (x, y) = self.corner.position();
+
+		let style = format!("fontsize={}:fontcolor={}:box=1:boxcolor=black@0.5:boxborderw=4", self.font_size, self.color);
+		let timecode = format!("drawtext=timecode='00\\:00\\:00\\:00':rate={fps}:x={x}:y={y}:{style}");
+
+		if self.frame_number {
+			format!("{timecode},drawtext=text='%{{frame_num}}':x={x}:y={y}+{}:{style}", self.font_size + 8)
+		} else {
+			timecode
+		}
+	}
+}
+
+/// Defines how frames are converted from the project frame rate to a different output frame rate
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FpsConversion {
+	/// Drops or duplicates whole frames
+	DropDuplicate,
+	/// Generates new frames using motion interpolation, which is smooth but very slow
+	Interpolate,
+	/// Blends neighbouring frames together
+	Blend
+}
+
+impl FpsConversion {
+	/// Returns the next [`FpsConversion`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::DropDuplicate => Self::Interpolate,
+			Self::Interpolate => Self::Blend,
+			Self::Blend => Self::DropDuplicate
+		}
+	}
+
+	/// Creates an `FFmpeg` filter converting to a specific frame rate
+	fn filter(self, fps: f32) -> String {
+		match self {
+			Self::DropDuplicate => format!("fps={fps}"),
+			Self::Interpolate => format!("minterpolate=fps={fps}:mi_mode=mci"),
+			Self::Blend => format!("framerate=fps={fps}")
+		}
+	}
+}
+
+/// Video codecs which can be picked for exports instead of leaving it to the container
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+	H264,
+	H265,
+	Vp9,
+	Av1
+}
+
+impl VideoCodec {
+	/// Returns the next [`VideoCodec`], where `None` lets the container decide, for cycling through them
+	pub const fn next(codec: Option<Self>) -> Option<Self> {
+		match codec {
+			None => Some(Self::H264),
+			Some(Self::H264) => Some(Self::H265),
+			Some(Self::H265) => Some(Self::Vp9),
+			Some(Self::Vp9) => Some(Self::Av1),
+			Some(Self::Av1) => None
+		}
+	}
+
+	/// Returns the `FFmpeg` encoder for the [`VideoCodec`]
+	const fn encoder(self) -> &'static str {
+		match self {
+			Self::H264 => "libx264",
+			Self::H265 => "libx265",
+			Self::Vp9 => "libvpx-vp9",
+			Self::Av1 => "libaom-av1"
+		}
+	}
+
+	/// Returns the pixel format frames are encoded in, where only VP9 keeps the `alpha` channel
+	const fn pix_fmt(self, alpha: bool) -> &'static str {
+		if alpha && matches!(self, Self::Vp9) { "yuva420p" } else { "yuv420p" }
+	}
+
+	/// Returns whether files with a lowercase extension can hold the [`VideoCodec`]
+	fn fits(self, extension: &str) -> bool {
+		match self {
+			Self::H264 => matches!(extension, "mp4" | "mov" | "mkv" | "avi"),
+			Self::H265 => matches!(extension, "mp4" | "mov" | "mkv"),
+			Self::Vp9 | Self::Av1 => matches!(extension, "webm" | "mp4" | "mkv")
+		}
+	}
+}
+
+/// Audio codecs which can be picked for exports instead of leaving it to the container
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AudioCodec {
+	Aac,
+	Opus,
+	Mp3,
+	Flac
+}
+
+impl AudioCodec {
+	/// Returns the next [`AudioCodec`], where `None` lets the container decide, for cycling through them
+	pub const fn next(codec: Option<Self>) -> Option<Self> {
+		match codec {
+			None => Some(Self::Aac),
+			Some(Self::Aac) => Some(Self::Opus),
+			Some(Self::Opus) => Some(Self::Mp3),
+			Some(Self::Mp3) => Some(Self::Flac),
+			Some(Self::Flac) => None
+		}
+	}
+
+	/// Returns the `FFmpeg` encoder for the [`AudioCodec`]
+	const fn encoder(self) -> &'static str {
+		match self {
+			Self::Aac => "aac",
+			Self::Opus => "libopus",
+			Self::Mp3 => "libmp3lame",
+			Self::Flac => "flac"
+		}
+	}
+
+	/// Returns whether files with a lowercase extension can hold the [`AudioCodec`]
+	fn fits(self, extension: &str) -> bool {
+		match self {
+			Self::Aac => matches!(extension, "mp4" | "mov" | "mkv"),
+			Self::Opus => matches!(extension, "webm" | "mp4" | "mkv"),
+			Self::Mp3 => matches!(extension, "mp4" | "mov" | "mkv" | "avi"),
+			Self::Flac => matches!(extension, "mp4" | "mkv")
+		}
+	}
+}
+
+/// Sample rate and channel count the audio of exports is mixed at
+const AUDIO_FORMAT: (u32, u16) = (48_000, 2);
+
+/// Constant rate factors which the export quality can be cycled through, lower being better, where `None` leaves it to the encoder
+pub const CRF_PRESETS: [Option<u8>; 4] = [None, Some(18), Some(23), Some(28)];
+
+/// Bitrates in kbit/s which the export bitrate can be cycled through, which take precedence over the constant rate factor when set
+pub const BITRATE_PRESETS: [Option<u32>; 4] = [None, Some(2_000), Some(8_000), Some(20_000)];
+
+/// Output resolutions and frame rates which exports can be cycled through, where `None` renders at the size of the window
+pub const SIZE_PRESETS: [Option<(u32, u32, f32)>; 5] = [
+	None,
+	Some((1920, 1080, 30.0)),
+	Some((1920, 1080, 60.0)),
+	Some((1280, 720, 30.0)),
+	Some((1280, 720, 60.0))
+];
+
+/// Frame rates which the output frame rate can be cycled through
+pub const OUTPUT_FPS_PRESETS: [Option<f32>; 6] = [None, Some(24.0), Some(25.0), Some(30.0), Some(50.0), Some(60.0)];
+
+/// Settings used when exporting the project
+pub struct ExportSettings {
+	/// Frame rate at which the project is rendered
+	pub fps: f32,
+	/// Resolution the project is rendered at, which is the size it's laid out at if `None`
+	pub size: Option<(u32, u32)>,
+	/// Frame rate of the exported file, if it differs from `fps`
+	pub output_fps: Option<f32>,
+	pub fps_conversion: FpsConversion,
+	pub timecode: Option<TimecodeOverlay>,
+	/// Codec the video is encoded with, which is picked by the container if `None`
+	pub codec: Option<VideoCodec>,
+	/// Constant rate factor the video is encoded at
+	pub crf: Option<u8>,
+	/// Bitrate in kbit/s the video is encoded at
+	pub bitrate: Option<u32>,
+	/// Codec the audio is encoded with, which is picked by the container if `None`
+	pub audio_codec: Option<AudioCodec>,
+	/// Whether only the marked loop region is exported, rather than the whole project
+	pub region_only: bool
+}
+
+impl Default for ExportSettings {
+	fn default() -> Self {
+		Self {
+			fps: 30.0,
+			size: None,
+			output_fps: None,
+			fps_conversion: FpsConversion::DropDuplicate,
+			timecode: None,
+			codec: None,
+			crf: None,
+			bitrate: None,
+			audio_codec: None,
+			region_only: false
+		}
+	}
+}
+
+impl ExportSettings {
+	/// Returns the frame rate of the exported file
+	pub fn output_fps(&self) -> f32 {
+		self.output_fps.unwrap_or(self.fps)
+	}
+
+	/// Creates the `FFmpeg` filtergraph applied to the rendered frames, if any
+	fn filter(&self) -> Option<String> {
+		let conversion = self.output_fps
+			.filter(|&fps| (fps - self.fps).abs() > f32::EPSILON)
+			.map(|fps| self.fps_conversion.filter(fps));
+
+		let timecode = self.timecode.as_ref().map(|timecode| timecode.filter(self.output_fps()));
+
+		match (conversion, timecode) {
+			(Some(conversion), Some(timecode)) => Some(format!("{conversion},{timecode}")),
+			(conversion, timecode) => conversion.or(timecode)
+		}
+	}
+}
+
+/// Returns the [`VideoCodec`] and [`AudioCodec`] used for a file, which are chosen by it's extension unless picked in the [`ExportSettings`]
+///
+/// Containers without a match are left to `FFmpeg`'s defaults, and picked codecs the container can't hold are an error
+fn codec(path: &Path, settings: &ExportSettings) -> io::Result<(Option<VideoCodec>, Option<AudioCodec>)> {
+	let extension = path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase).unwrap_or_default();
+
+	let unfit = |encoder: &str| Err(io::Error::new(
+		io::ErrorKind::InvalidInput,
+		format!("{encoder} can't be stored in .{extension} files")
+	));
+
+	let video = match settings.codec {
+		Some(codec) if !codec.fits(&extension) => return unfit(codec.encoder()),
+		Some(codec) => Some(codec),
+		None => match extension.as_str() {
+			"mp4" | "mov" | "avi" | "mkv" => Some(VideoCodec::H264),
+			"webm" => Some(VideoCodec::Vp9),
+			_ => None
+		}
+	};
+
+	// The quality is set through the encoder, so it can't be left to `FFmpeg` along with the codec
+	if video.is_none() && (settings.crf.is_some() || settings.bitrate.is_some()) {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("Pick a video codec to set the quality of .{extension} files")
+		));
+	}
+
+	let audio = match settings.audio_codec {
+		Some(codec) if !codec.fits(&extension) => return unfit(codec.encoder()),
+		Some(codec) => Some(codec),
+		None => match extension.as_str() {
+			"mp4" | "mov" => Some(AudioCodec::Aac),
+			"webm" => Some(AudioCodec::Opus),
+			"avi" => Some(AudioCodec::Mp3),
+			_ => None
+		}
+	};
+
+	Ok((video, audio))
+}
+
+/// Returns the time range covered by the project, from the start of the first [`Video`] to the end of the last
+///
+/// This is the one place the project's length is worked out, for the timeline, exports and looping alike, so an empty project, or one with no finite times, covers `0.0..=0.0`
+pub fn project_range(videos: &[Video]) -> RangeInclusive<f32> {
+	let start = videos.iter().map(|video| *video.duration.start()).filter(|start| start.is_finite()).fold(f32::INFINITY, f32::min);
+	let end = videos.iter().map(Video::end).filter(|end| end.is_finite()).fold(0.0, f32::max);
+
+	start.min(end)..=end
+}
+
+/// Returns the time at which the last [`Video`] which ends by itself does, after which playback stops
+///
+/// [`Video`]s that loop or hold their last frame are left out, and if all of them do, the project never ends
+pub fn project_end(videos: &[Video]) -> Option<f32> {
+	videos.iter()
+		.filter(|video| !video.is_endless())
+		.map(Video::end)
+		.filter(|end| end.is_finite())
+		.reduce(f32::max)
+}
+
+/// Returns the pixels of a rendered frame as the straight alpha `rgba` which `FFmpeg` reads, demultiplying them into `buffer` when they can be translucent
+///
+/// `tiny-skia` premultiplies alpha, which only makes a difference where the `background` frames are rendered over isn't opaque
+fn straight_alpha<'a>(pixmap: &'a Pixmap, background: Color, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+	if background.alpha() >= 1.0 {
+		return pixmap.data();
+	}
+
+	buffer.clear();
+	buffer.extend(pixmap.pixels().iter().flat_map(|pixel| {
+		let color = pixel.demultiply();
+		[color.red(), color.green(), color.blue(), color.alpha()]
+	}));
+
+	buffer
+}
+
+/// Mixes the audio and encodes it losslessly into a file, which is done in the background while the frames are rendered
+fn encode_audio(mix: audio::Mix, path: &Path, stop: &AtomicBool) -> io::Result<()> {
+	let (sample_rate, channels) = AUDIO_FORMAT;
+
+	let mut ffmpeg = ffmpeg::command()
+		.hide_banner()
+		.create_no_window()
+		.format("f32le")
+		.args(["-ar", &sample_rate.to_string(), "-ac", &channels.to_string()])
+		.input("-")
+		.codec_audio("flac")
+		.overwrite()
+		.output(path.to_string_lossy())
+		.spawn()?;
+
+	let mut stdin = ffmpeg.take_stdin().ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "FFmpeg has no stdin"))?;
+
+	let iter = ffmpeg.iter().map_err(|error| io::Error::other(error.to_string()))?;
+	let log = thread::spawn(move || iter.for_each(drop));
+
+	let result = mix.write(&mut stdin, stop);
+	drop(stdin); // Closing stdin lets `FFmpeg` finish
+	drop(log.join());
+
+	let status = ffmpeg.wait()?;
+	result?;
+
+	if status.success() {
+		Ok(())
+	} else {
+		Err(io::Error::other(format!("FFmpeg exited with {status} while encoding the audio")))
+	}
+}
+
+/// Audio of an [`Export`], which is mixed alongside the frames being rendered and muxed with them once they're done
+///
+/// `FFmpeg`'s stdin is taken by the frames, so both are encoded into temporary files first
+struct AudioExport {
+	/// File the frames are encoded into until they're muxed with the audio
+	video: PathBuf,
+	/// File the mixed audio is encoded into
+	audio: PathBuf,
+	codec: Option<AudioCodec>,
+	stop: Arc<AtomicBool>,
+	thread: JoinHandle<io::Result<()>>
+}
+
+impl AudioExport {
+	/// Starts mixing the audio in the background, for frames encoded into a file with an `extension`
+	fn start(mix: audio::Mix, extension: &str, codec: Option<AudioCodec>) -> Self {
+		let temporary = |name: &str| std::env::temp_dir().join(format!("dusk-export-{}-{name}", std::process::id()));
+
+		let audio = temporary("audio.mka");
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let thread = {
+			let (audio, stop) = (audio.clone(), stop.clone());
+			thread::spawn(move || encode_audio(mix, &audio, &stop))
+		};
+
+		Self {
+			video: temporary(&format!("video.{extension}")),
+			audio,
+			codec,
+			stop,
+			thread
+		}
+	}
+
+	/// Waits for the audio to be mixed and muxes it with the frames into `path`, encoding it with the picked [`AudioCodec`]
+	fn finish(self, path: &Path) -> io::Result<()> {
+		let result = self.thread.join()
+			.unwrap_or_else(|_| Err(io::Error::other("Mixing the audio failed")))
+			.and_then(|()| {
+				let mut command = ffmpeg::command();
+
+				command
+					.hide_banner()
+					.create_no_window()
+					.input(self.video.to_string_lossy())
+					.input(self.audio.to_string_lossy())
+					.map("0:v")
+					.map("1:a")
+					.codec_video("copy");
+
+				if let Some(codec) = self.codec {
+					command.codec_audio(codec.encoder());
+				}
+
+				let mut ffmpeg = command.overwrite().output(path.to_string_lossy()).spawn()?;
+
+				// The log has to be read for `FFmpeg` not to block on it
+				ffmpeg.iter().map_err(|error| io::Error::other(error.to_string()))?.for_each(drop);
+
+				let status = ffmpeg.wait()?;
+
+				if status.success() {
+					Ok(())
+				} else {
+					Err(io::Error::other(format!("FFmpeg exited with {status} while adding the audio")))
+				}
+			});
+
+		drop(fs::remove_file(&self.video));
+		drop(fs::remove_file(&self.audio));
+
+		result
+	}
+
+	/// Stops mixing the audio and removes the temporary files
+	fn cancel(self) {
+		self.stop.store(true, Relaxed);
+		drop(self.thread.join());
+
+		drop(fs::remove_file(&self.video));
+		drop(fs::remove_file(&self.audio));
+	}
+}
+
+/// An export in progress, which renders and encodes one frame at a time so that the window can keep responding in between
+pub struct Export {
+	path: PathBuf,
+	ffmpeg: FfmpegChild,
+	stdin: Option<ChildStdin>,
+	/// Audio being mixed alongside the frames, if any [`Video`] is heard
+	audio: Option<AudioExport>,
+	/// Thread reading `FFmpeg`'s log, which blocks if it isn't read
+	log: Option<JoinHandle<()>>,
+	pixmap: Pixmap,
+	/// Frame converted to straight alpha before it's encoded, kept between frames to avoid allocating
+	straight: Vec<u8>,
+	/// Transform from the size the project is laid out at to the `pixmap`, which scales it evenly and centers it
+	view: Transform,
+	start: f32,
+	fps: f32,
+	background: Color,
+	/// Number of frames rendered so far
+	frame: u32,
+	frames: u32,
+	started: Instant
+}
+
+impl Export {
+	/// Starts `FFmpeg` encoding into a file, ready for every frame in `range` to be rendered with [`Export::step`]
+	///
+	/// The [`Video`]s are laid out in a canvas of `size`, which is scaled to the resolution in the [`ExportSettings`]
+	///
+	/// The output container is picked by `FFmpeg` from the extension of `path`, and the audio of every [`Video`] heard in `range` is mixed into it
+	pub fn start(
+		path: &Path,
+		videos: &mut [Video],
+		size: (u32, u32),
+		range: RangeInclusive<f32>,
+		background: Color,
+		settings: &ExportSettings
+	) -> io::Result<Self> {
+		let (width, height) = settings.size.unwrap_or(size);
+		let pixmap = Pixmap::new(width, height).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid export resolution"))?;
+		let (codec, audio_codec) = codec(path, settings)?;
+		let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+
+		// The layout is scaled evenly to fit the resolution, leaving bars on two sides if their aspect ratios differ
+		let scale = (width as f32 / size.0.max(1) as f32).min(height as f32 / size.1.max(1) as f32);
+
+		let view = Transform::from_scale(scale, scale).post_translate(
+			((size.0 as f32).mul_add(-scale, width as f32) / 2.0).round(),
+			((size.1 as f32).mul_add(-scale, height as f32) / 2.0).round()
+		);
+
+		// Exports are never made from lowered quality preview frames, are decoded at the size they're rendered at, and as fast as possible
+		for video in &mut *videos {
+			video.set_quality(DecodeQuality::Full);
+			video.set_render_scale(scale);
+			video.set_playback_speed(None);
+		}
+
+		let mut command = ffmpeg::command();
+
+		command
+			.hide_banner()
+			.create_no_window()
+			.format("rawvideo")
+			.pix_fmt("rgba")
+			.size(width, height)
+			.rate(settings.fps)
+			.input("-");
+
+		let audio = audio::mix(videos, &range, AUDIO_FORMAT).map(|mix| AudioExport::start(mix, extension, audio_codec));
+
+		if let Some(filter) = settings.filter() {
+			command.filter(filter);
+		}
+
+		if let Some(codec) = codec {
+			command
+				.codec_video(codec.encoder())
+				.pix_fmt(codec.pix_fmt(background.alpha() < 1.0)); // Frames can only be see-through where the background is
+
+			if let Some(bitrate) = settings.bitrate {
+				command.args(["-b:v", &format!("{bitrate}k")]);
+			} else if let Some(crf) = settings.crf {
+				command.args(["-crf", &crf.to_string()]);
+
+				// VP9 and AV1 are otherwise capped at a default bitrate
+				if matches!(codec, VideoCodec::Vp9 | VideoCodec::Av1) {
+					command.args(["-b:v", "0"]);
+				}
+			}
+		}
+
+		// With audio, the frames are muxed with it into `path` once they're done
+		let output = audio.as_ref().map_or(path, |audio| audio.video.as_path());
+
+		let mut ffmpeg = match command.overwrite().output(output.to_string_lossy()).spawn() {
+			Ok(ffmpeg) => ffmpeg,
+			Err(error) => {
+				if let Some(audio) = audio {
+					audio.cancel();
+				}
+
+				return Err(error);
+			}
+		};
+
+		let stdin = ffmpeg.take_stdin().ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "FFmpeg has no stdin"))?;
+
+		let iter = ffmpeg.iter().map_err(|error| io::Error::other(error.to_string()))?;
+		let log = thread::spawn(move || iter.for_each(drop));
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			ffmpeg,
+			stdin: Some(stdin),
+			audio,
+			log: Some(log),
+			pixmap,
+			straight: Vec::new(),
+			view,
+			start: *range.start(),
+			fps: settings.fps,
+			background,
+			frame: 0,
+			frames: ((range.end() - range.start()) * settings.fps).ceil() as u32,
+			started: Instant::now()
+		})
+	}
+
+	/// Renders the next frame with [`render_frame`] and encodes it, returning whether there are any frames left
+	pub fn step(&mut self, videos: &mut [Video]) -> io::Result<bool> {
+		if self.frame >= self.frames {
+			return Ok(false);
+		}
+
+		let time = self.time();
+		render_frame(&mut self.pixmap.as_mut(), videos, time, self.background, self.view, ScaleQuality::Bicubic, usize::MAX); // Every frame has to be exact
+
+		let data = straight_alpha(&self.pixmap, self.background, &mut self.straight);
+
+		self.stdin.as_mut()
+			.ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "FFmpeg has no stdin"))?
+			.write_all(data)?;
+
+		self.frame += 1;
+
+		Ok(self.frame < self.frames)
+	}
+
+	/// Returns the file being exported to
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Returns the timestamp of the next frame
+	pub fn time(&self) -> f32 {
+		(self.frame as f32).mul_add(self.fps.recip(), self.start)
+	}
+
+	/// Returns the last rendered frame
+	pub const fn frame(&self) -> &Pixmap {
+		&self.pixmap
+	}
+
+	/// Returns how much of the export is done, from `0.0` to `1.0`
+	///
+	/// This counts the frames rendered rather than reading `FFmpeg`'s progress reports, which only lag behind by the frames waiting in the pipe to it
+	pub fn progress(&self) -> f32 {
+		if self.frames == 0 {
+			1.0
+		} else {
+			self.frame as f32 / self.frames as f32
+		}
+	}
+
+	/// Returns an estimate of how long the rest of the export takes, based on how long the frames so far took
+	pub fn remaining(&self) -> Option<Duration> {
+		(self.frame > 0).then(|| self.started.elapsed().mul_f32((self.frames - self.frame) as f32 / self.frame as f32))
+	}
+
+	/// Waits for `FFmpeg` to finish writing the file
+	///
+	/// With audio, this also waits for it to be mixed and muxes it in
+	pub fn finish(mut self) -> io::Result<()> {
+		drop(self.stdin.take()); // Signals the end of input
+		self.join_log();
+
+		let result = self.ffmpeg.wait().and_then(|status| if status.success() {
+			Ok(())
+		} else {
+			Err(io::Error::other(format!("FFmpeg exited with {status}")))
+		});
+
+		match self.audio.take() {
+			Some(audio) if result.is_ok() => audio.finish(&self.path),
+			Some(audio) => {
+				audio.cancel();
+				result
+			},
+			None => result
+		}
+	}
+
+	/// Stops the export, quitting `FFmpeg` and removing the unfinished file
+	pub fn cancel(mut self) -> io::Result<()> {
+		drop(self.stdin.take());
+		drop(self.ffmpeg.kill());
+		drop(self.ffmpeg.wait());
+		self.join_log();
+
+		// With audio, nothing has been written to `path` yet
+		if let Some(audio) = self.audio.take() {
+			audio.cancel();
+			return Ok(());
+		}
+
+		fs::remove_file(&self.path)
+	}
+
+	/// Waits for the thread reading `FFmpeg`'s log to finish, which it does once `FFmpeg` exits
+	fn join_log(&mut self) {
+		if let Some(log) = self.log.take() {
+			drop(log.join());
+		}
+	}
+}
+
+/// Renders every frame in `range` with [`render_frame`] and encodes them into a file using `FFmpeg`, blocking until it's done
+///
+/// The output container is picked by `FFmpeg` from the extension of `path`
+pub fn export(
+	path: &Path,
+	videos: &mut [Video],
+	size: (u32, u32),
+	range: RangeInclusive<f32>,
+	background: Color,
+	settings: &ExportSettings
+) -> io::Result<()> {
+	let mut export = Export::start(path, videos, size, range, background, settings)?;
+
+	loop {
+		match export.step(videos) {
+			Ok(true) => (),
+			Ok(false) => return export.finish(),
+			Err(error) => {
+				drop(export.finish());
+				return Err(error);
+			}
+		}
+	}
+}
+
+/// Renders the project at `time` with [`render_frame`] and saves it as a PNG image using `FFmpeg`, blocking until it's done
+///
+/// Like exports, the frame is rendered at full quality and with every [`Video`] decoded, so the same project always gives the same image
+pub fn export_frame(path: &Path, videos: &mut [Video], (width, height): (u32, u32), time: f32, background: Color) -> io::Result<()> {
+	let mut pixmap = Pixmap::new(width, height).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid export resolution"))?;
+
+	for video in &mut *videos {
+		video.set_quality(DecodeQuality::Full);
+		video.set_playback_speed(None);
+	}
+
+	render_frame(&mut pixmap.as_mut(), videos, time, background, Transform::identity(), ScaleQuality::Bicubic, usize::MAX);
+
+	let mut ffmpeg = ffmpeg::command()
+		.hide_banner()
+		.create_no_window()
+		.format("rawvideo")
+		.pix_fmt("rgba")
+		.size(width, height)
+		.input("-")
+		.frames(1)
+		.codec_video("png")
+		.overwrite()
+		.output(path.to_string_lossy())
+		.spawn()?;
+
+	let mut stdin = ffmpeg.take_stdin().ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "FFmpeg has no stdin"))?;
+	stdin.write_all(straight_alpha(&pixmap, background, &mut Vec::new()))?;
+	drop(stdin); // Closing stdin lets `FFmpeg` finish
+
+	// The log has to be read for `FFmpeg` not to block on it
+	ffmpeg.iter().map_err(|error| io::Error::other(error.to_string()))?.for_each(drop);
+
+	if ffmpeg.wait()?.success() {
+		Ok(())
+	} else {
+		Err(io::Error::other("FFmpeg couldn't write the image"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn translucent_frames_are_demultiplied() {
+		let mut pixmap = Pixmap::new(1, 1).unwrap();
+		pixmap.fill(Color::from_rgba8(255, 0, 0, 128));
+		assert_eq!(pixmap.data(), [128, 0, 0, 128]);
+
+		let mut buffer = Vec::new();
+		assert_eq!(straight_alpha(&pixmap, Color::TRANSPARENT, &mut buffer), [255, 0, 0, 128]);
+
+		// Over an opaque background, frames are written as they are
+		assert_eq!(straight_alpha(&pixmap, Color::BLACK, &mut buffer), pixmap.data());
+	}
+}
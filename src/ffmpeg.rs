@@ -0,0 +1,39 @@
+use std::{path::{Path, PathBuf}, sync::{Mutex, OnceLock}};
+
+use ffmpeg_sidecar::{command::FfmpegCommand, version::ffmpeg_version_with_path};
+
+/// `FFmpeg` executable picked by the user, used instead of the one `ffmpeg_sidecar` finds
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Hardware acceleration backends which decoding can be set to use, `none` being the fallback when the others fail
+pub const HWACCELS: [&str; 6] = ["auto", "none", "cuda", "vaapi", "d3d11va", "videotoolbox"];
+
+/// Hardware acceleration backend used when decoding new videos
+static HWACCEL: Mutex<&str> = Mutex::new(HWACCELS[0]);
+
+/// Makes every later [`command()`] use a specific `FFmpeg` executable
+pub fn set_path(path: PathBuf) {
+	drop(PATH.set(path));
+}
+
+/// Returns whether a file is a working `FFmpeg` executable, by running it with `-version`
+pub fn is_valid(path: &Path) -> bool {
+	ffmpeg_version_with_path(path).is_ok()
+}
+
+/// Creates an [`FfmpegCommand`] using the picked `FFmpeg` executable, if any
+pub fn command() -> FfmpegCommand {
+	PATH.get().map_or_else(FfmpegCommand::new, FfmpegCommand::new_with_path)
+}
+
+/// Sets the hardware acceleration backend used when decoding new videos, ignoring any not in [`HWACCELS`]
+pub fn set_hwaccel(hwaccel: &str) {
+	if let Some(&hwaccel) = HWACCELS.iter().find(|&&known| known == hwaccel) {
+		*HWACCEL.lock().unwrap() = hwaccel;
+	}
+}
+
+/// Returns the hardware acceleration backend used when decoding new videos
+pub fn hwaccel() -> &'static str {
+	*HWACCEL.lock().unwrap()
+}
@@ -0,0 +1,109 @@
+use std::{ffi::OsStr, path::Path};
+
+use crate::ffmpeg;
+
+/// Video file extensions Dusk knows about, with the name of the `FFmpeg` demuxer reading them
+const VIDEO: [(&str, &str); 13] = [
+	("mp4", "mp4"),
+	("mkv", "matroska"),
+	("webm", "webm"),
+	("mov", "mov"),
+	("avi", "avi"),
+	("gif", "gif"),
+	("flv", "flv"),
+	("ts", "mpegts"),
+	("m4v", "mp4"),
+	("mpg", "mpeg"),
+	("wmv", "asf"),
+	("ogv", "ogg"),
+	("3gp", "3gp")
+];
+
+/// Image file extensions Dusk knows about, with the name of the `FFmpeg` demuxer reading them
+const IMAGE: [(&str, &str); 6] = [
+	("png", "png_pipe"),
+	("jpg", "jpeg_pipe"),
+	("jpeg", "jpeg_pipe"),
+	("webp", "webp_pipe"),
+	("bmp", "bmp_pipe"),
+	("tiff", "tiff_pipe")
+];
+
+/// Container extensions which can be exported to, with the name of the `FFmpeg` muxer writing them
+const OUTPUT: [(&str, &str); 6] = [
+	("webm", "webm"),
+	("mp4", "mp4"),
+	("mov", "mov"),
+	("mkv", "matroska"),
+	("avi", "avi"),
+	("gif", "gif")
+];
+
+/// Returns the extensions of `formats` whose format is listed in `names`, or every extension if the list couldn't be queried
+fn supported(formats: &[(&'static str, &str)], names: Option<&[String]>) -> Vec<&'static str> {
+	formats.iter()
+		.filter(|(_, format)| names.map_or(true, |names| names.iter().any(|name| name == format)))
+		.map(|&(extension, _)| extension)
+		.collect()
+}
+
+/// Runs `FFmpeg` with an argument listing formats, such as `-demuxers`, and returns the names of every listed format
+///
+/// Formats with several names, such as `mov,mp4,m4a`, are split into each name
+fn list(argument: &str) -> Option<Vec<String>> {
+	let output = ffmpeg::command()
+		.hide_banner()
+		.create_no_window()
+		.arg(argument)
+		.as_inner_mut()
+		.output()
+		.ok()?;
+
+	let stdout = String::from_utf8_lossy(&output.stdout);
+
+	// The list starts after a `--` line
+	let names = stdout.lines()
+		.skip_while(|line| line.trim() != "--")
+		.skip(1)
+		.filter_map(|line| line.split_whitespace().nth(1))
+		.flat_map(|names| names.split(','))
+		.map(String::from)
+		.collect();
+
+	Some(names)
+}
+
+/// File extensions which the installed `FFmpeg` can read and write, queried once at startup for the import and export dialogs
+pub struct Formats {
+	pub video: Vec<&'static str>,
+	pub image: Vec<&'static str>,
+	pub output: Vec<&'static str>
+}
+
+impl Formats {
+	/// Queries `FFmpeg` for it's demuxers and muxers, falling back to every known extension if that fails
+	pub fn query() -> Self {
+		let demuxers = list("-demuxers");
+		let muxers = list("-muxers");
+
+		Self {
+			video: supported(&VIDEO, demuxers.as_deref()),
+			image: supported(&IMAGE, demuxers.as_deref()),
+			output: supported(&OUTPUT, muxers.as_deref())
+		}
+	}
+
+	/// Returns whether the file at `path` can be imported as a video or image, judging by it's extension
+	pub fn can_import(&self, path: &Path) -> bool {
+		path.extension()
+			.and_then(OsStr::to_str)
+			.is_some_and(|extension| self.video.iter().chain(&self.image).any(|input| input.eq_ignore_ascii_case(extension)))
+	}
+
+	/// Returns whether a file can be exported to `path`, judging by it's extension
+	pub fn can_export(&self, path: &Path) -> bool {
+		path.extension()
+			.and_then(OsStr::to_str)
+			.is_some_and(|extension| self.output.iter().any(|output| output.eq_ignore_ascii_case(extension)))
+	}
+}
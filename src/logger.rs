@@ -0,0 +1,33 @@
+use std::env;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Prints log messages to stderr, only letting through warnings and errors from other crates
+struct Logger;
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= log::max_level() && (metadata.level() <= Level::Warn || metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+	}
+
+	fn log(&self, record: &Record) {
+		if self.enabled(record.metadata()) {
+			eprintln!("[{}] {}", record.level(), record.args());
+		}
+	}
+
+	fn flush(&self) {}
+}
+
+/// Installs the [`Logger`] at the level set by the `RUST_LOG` environment variable, such as `debug` or `trace`
+///
+/// Without one, release builds only log warnings and errors, while debug builds log info as well
+pub fn init() {
+	let level = env::var("RUST_LOG").ok()
+		.and_then(|level| level.parse().ok())
+		.unwrap_or(if cfg!(debug_assertions) { LevelFilter::Info } else { LevelFilter::Warn });
+
+	if log::set_logger(&Logger).is_ok() {
+		log::set_max_level(level);
+	}
+}
@@ -41,24 +41,91 @@
 	clippy::cast_lossless
 )]
 
+#![allow(dropping_copy_types)] // Unused results are discarded with `drop` regardless of their type
+
+mod audio;
+mod cache;
+#[cfg(feature = "control")]
+mod control;
+mod edit;
+mod export;
+mod ffmpeg;
+mod formats;
+mod logger;
+mod project;
+mod region;
 mod render;
+mod settings;
+mod source;
+mod thumbnails;
+mod timeline;
 mod video;
+mod waveforms;
 
-use std::{num::NonZeroU32, time::Instant, env::current_dir, rc::Rc};
+use std::{collections::HashSet, num::{NonZeroU32, NonZeroUsize}, time::{Duration, Instant}, env::current_dir, process::ExitCode, rc::Rc};
 
 use emath::lerp;
 use ffmpeg_sidecar::{command::ffmpeg_is_installed, ffprobe::ffprobe_is_installed};
 use rayon_macro::parallel;
-use rfd::{FileDialog, MessageDialog};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult};
 use softbuffer::{Context, Surface};
-use tiny_skia::{BlendMode, Color, FillRule, LineJoin, Paint, Path, PathBuilder, Pixmap, PixmapMut, PremultipliedColorU8, Rect, Shader, Stroke, Transform, ALPHA_U8_OPAQUE};
-use winit::{event_loop::{EventLoop, DeviceEvents}, window::{WindowBuilder, Icon, Theme, CursorIcon, Fullscreen}, dpi::{LogicalSize, PhysicalPosition}, event::{Event, WindowEvent, KeyEvent, ElementState, MouseScrollDelta}, keyboard::{Key, NamedKey}};
+use tiny_skia::{BlendMode, Color, FillRule, IntRect, LineJoin, Paint, Path, PathBuilder, Pixmap, PixmapMut, PixmapPaint, PremultipliedColorU8, Rect, Shader, Stroke, Transform, ALPHA_U8_OPAQUE};
+use winit::{event_loop::{ControlFlow, EventLoop, DeviceEvents}, window::{WindowBuilder, Icon, Theme, CursorIcon, Fullscreen}, dpi::{LogicalSize, PhysicalPosition, PhysicalSize}, event::{Event, WindowEvent, KeyEvent, ElementState, MouseButton, MouseScrollDelta}, keyboard::{Key, NamedKey, ModifiersState}};
+
+use audio::{AudioOutput, AudioScrub, Playback, MAX_VOLUME};
+use edit::{paste, ripple_remove, split, EditCommand, History};
+use formats::Formats;
+use export::{export, export_frame, project_end, project_range, AudioCodec, Export, ExportSettings, FpsConversion, TimecodeOverlay, VideoCodec, BITRATE_PRESETS, CRF_PRESETS, OUTPUT_FPS_PRESETS, SIZE_PRESETS};
+use project::Project;
+use region::LoopRegion;
+use render::{draw_order, render_frame};
+use settings::{Placement, Settings};
+use source::{is_url, Sources};
+use thumbnails::Thumbnails;
+use timeline::TimelineView;
+use video::{DecodeQuality, Drag, Grade, ScaleMode, SeekMode, Video, VideoError, VideoState, HIGH_FPS};
+use waveforms::Waveforms;
+
+/// Canvas colors which can be picked instead of the one following the system theme, the last being transparent for exporting with alpha
+const BACKGROUND_PRESETS: [(u8, u8, u8, u8); 4] = [(0, 0, 0, 255), (255, 255, 255, 255), (0, 177, 64, 255), (0, 0, 0, 0)];
+
+/// Seconds over which clips fade in or out when fades are toggled
+const FADE_LENGTH: f32 = 1.0;
+
+/// Longest time spent exporting frames at a time, before letting the window respond again
+const EXPORT_BUDGET: Duration = Duration::from_millis(50);
+
+/// Width in pixels of the edges of clips in the timeline which trim them when dragged
+const TRIM_HANDLE: f32 = 4.0;
+
+/// Distance in pixels within which clips dragged along the timeline snap to other clips and the playhead
+const SNAP_DISTANCE: f32 = 6.0;
+
+/// Distance in pixels within which the edges or center of a clip dragged on the canvas snap to the guides
+const GUIDE_SNAP_DISTANCE: i32 = 8;
 
-use render::render_frame;
-use video::{Drag, Video};
+/// Seconds an arrow key has to be held before the playhead starts moving continuously
+const SCRUB_HOLD_DELAY: f32 = 0.3;
+/// Steps per second the playhead moves by while an arrow key is held, before it speeds up
+const HELD_SCRUB_RATE: f32 = 4.0;
+/// Largest factor held scrubbing speeds up by, which it doubles towards every second
+const MAX_SCRUB_ACCELERATION: f32 = 32.0;
 
-const VIDEO_EXTENSIONS: &[&str; 5] = &["webm", "mp4", "mov", "avi", "gif"];
-const IMAGE_EXTENSIONS: &[&str; 4] = &["png", "jpg", "jpeg", "webp"];
+/// Slowest playback speed or clip rate reachable by halving it
+const MIN_SPEED: f32 = 0.0625;
+/// Fastest playback speed or clip rate reachable by doubling it
+const MAX_SPEED: f32 = 16.0;
+
+/// Part of a clip grabbed in the timeline, which decides whether dragging it moves or trims it
+#[derive(Clone, Copy)]
+enum ClipGrab {
+	/// Moves the whole clip, which was grabbed this many seconds after it's start
+	Body(f32),
+	/// Trims the start of the clip, keeping the rest of it's content in place
+	Head,
+	/// Trims the end of the clip
+	Tail
+}
 
 #[derive(PartialEq, Eq)]
 enum ClickState {
@@ -91,15 +158,370 @@ fn stroke_fill_path(
 	);
 }
 
-fn main() {
-	if !ffmpeg_is_installed() {
+/// Returns the bar along the bottom of the window which the playhead can be scrubbed across with the mouse, scaled by how visible the GUI is
+fn timeline_bar(width: f32, height: f32, gui: f32) -> Option<Rect> {
+	let h = height * 0.012 * gui;
+
+	Rect::from_xywh(0.0, height - h, width, h)
+}
+
+/// Returns the menu above the timeline bar in which clips are laid out along the timeline, scaled by how visible the GUI is
+fn timeline_menu(width: f32, height: f32, gui: f32) -> Option<Rect> {
+	let w = width * (gui - 0.5).max(0.0).mul_add(1.5, 0.05);
+	let h = height * gui.min(0.5) * 0.15;
+
+	Rect::from_xywh(
+		width.mul_add(0.5, w * -0.5),
+		height.mul_add(-0.015 * gui, height - h),
+		w,
+		h
+	)
+}
+
+/// Returns the width of the lines drawn in the menu
+fn menu_line(width: f32, height: f32) -> f32 {
+	width.min(height) * 0.0025
+}
+
+/// Returns the visible part of the preview of the `i`th clip in the menu, with each clip raised slightly above the one before it
+///
+/// Clips are shown until their [`Video::end`], which is known from their source's length before they're played
+fn clip_preview(menu: Rect, line: f32, i: usize, video: &Video, view: &TimelineView) -> Option<Rect> {
+	let left = menu.left() + line;
+	let right = menu.right() - line;
+
+	Rect::from_ltrb(
+		view.x(*video.duration.start(), left).clamp(left, right),
+		(i as f32).mul_add(-5.0, menu.top() + line),
+		view.x(video.end(), left).clamp(left, right),
+		(i as f32).mul_add(-5.0, menu.bottom() - line)
+	).filter(|preview| preview.width() > 0.0)
+}
+
+/// Returns where a clip starting at `start` and lasting `length` starts once whichever of it's edges is nearest to one of the `targets` is lined up with it, if that's within `threshold` seconds
+fn snap_clip(start: f32, length: f32, targets: &[f32], threshold: f32) -> f32 {
+	targets.iter()
+		.flat_map(|&target| [target - start, target - (start + length)])
+		.filter(|offset| offset.abs() <= threshold)
+		.min_by(|a, b| a.abs().total_cmp(&b.abs()))
+		.map_or(start, |offset| (start + offset).max(0.0))
+}
+
+/// Returns the guides along one axis of a canvas `length` pixels long, which are it's edges, thirds and center
+const fn guides(length: u32) -> [i32; 5] {
+	let length = length as i32;
+	[0, length / 3, length / 2, length * 2 / 3, length]
+}
+
+/// Returns where a clip `size` pixels long placed at `position` along one axis of the canvas ends up once whichever of it's edges or center is nearest to a guide is lined up with it, if that's within [`GUIDE_SNAP_DISTANCE`]
+fn snap_to_guides(position: i32, size: u32, length: u32) -> i32 {
+	let size = size as i32;
+
+	guides(length).into_iter()
+		.flat_map(|guide| [guide - position, guide - (position + size / 2), guide - (position + size)])
+		.filter(|offset| offset.abs() <= GUIDE_SNAP_DISTANCE)
+		.min_by_key(|offset| offset.abs())
+		.map_or(position, |offset| position + offset)
+}
+
+/// Returns the canvas color following a theme, which is also what headless exports are rendered over
+fn theme_background(theme: Theme) -> Color {
+	match theme {
+		Theme::Dark => Color::from_rgba8(25, 25, 35, 255),
+		Theme::Light => Color::from_rgba8(225, 225, 235, 255)
+	}
+}
+
+/// Returns the color of the canvas, which is either one of the [`BACKGROUND_PRESETS`] or the `background` following the theme
+fn canvas_color(background: Color, preset: Option<usize>) -> Color {
+	preset.map_or(background, |i| {
+		let (r, g, b, a) = BACKGROUND_PRESETS[i];
+		Color::from_rgba8(r, g, b, a)
+	})
+}
+
+/// Tells the user that an export failed
+fn show_export_error(path: &std::path::Path, error: &std::io::Error) {
+	MessageDialog::new()
+		.set_level(rfd::MessageLevel::Error)
+		.set_title("Export failed")
+		.set_description(format!("{} couldn't be exported: {error}", path.display()))
+		.show();
+}
+
+/// Returns whether the top left corner of a window at `position`, where it's title bar usually is, lies on any monitor
+fn is_on_screen<T>(event_loop: &EventLoop<T>, position: PhysicalPosition<i32>) -> bool {
+	// How much of the title bar has to be visible for the window to be dragged back
+	const GRIP: i32 = 64;
+
+	event_loop.available_monitors().any(|monitor| {
+		let origin = monitor.position();
+		let size = monitor.size();
+
+		(origin.x..origin.x + size.width as i32 - GRIP).contains(&position.x) &&
+		(origin.y..origin.y + size.height as i32 - GRIP).contains(&position.y)
+	})
+}
+
+/// Returns the topmost selected [`Video`], which edits of a single clip apply to, if any
+fn selected_video<'a>(videos: &'a mut [Video], selection: &HashSet<u64>) -> Option<&'a mut Video> {
+	let i = draw_order(videos).into_iter().rev().find(|&i| selection.contains(&videos[i].id))?;
+
+	Some(&mut videos[i])
+}
+
+/// Returns where a newly added clip is placed, as it's start and track
+///
+/// Clips are added to the track of the topmost selected clip, or the bottom track if there's no selection
+fn placement(videos: &[Video], selection: &HashSet<u64>, placement: Placement, playhead: f32) -> (f32, u8) {
+	let track = draw_order(videos).into_iter().rev().map(|i| &videos[i]).find(|video| selection.contains(&video.id)).map_or(0, |video| video.track);
+
+	let start = match placement {
+		Placement::Playhead => playhead,
+		Placement::ProjectStart => *project_range(videos).start(),
+		Placement::Append => videos.iter()
+			.filter(|video| video.track == track)
+			.map(Video::end)
+			.fold(0.0, f32::max)
+	};
+
+	(start, track)
+}
+
+/// Creates a [`Video`] placed according to the [`Placement`] set in the [`Settings`], showing an error if it can't be added
+fn add_video(path: &std::path::Path, videos: &[Video], selection: &HashSet<u64>, settings: &Settings, playhead: f32, sources: &mut Sources) -> Option<Video> {
+	let (start, track) = placement(videos, selection, settings.placement, playhead);
+
+	match Video::new(path.to_path_buf(), start, sources) {
+		Ok(mut video) => {
+			video.track = track;
+			ask_slow_motion(&mut video);
+			Some(video)
+		},
+		Err(error) => {
+			show_video_error(path, &error);
+			None
+		}
+	}
+}
+
+/// Selects a clicked clip, adding it to the `selection` while Shift is held
+///
+/// Clicking a clip that's already selected keeps the rest of the selection, so that they can be dragged together
+fn select(selection: &mut HashSet<u64>, id: u64, modifiers: ModifiersState) {
+	if !modifiers.shift_key() && !selection.contains(&id) {
+		selection.clear();
+	}
+
+	selection.insert(id);
+}
+
+/// Returns the nearest start or end of a clip after a timestamp, or before it if not `forward`
+fn clip_boundary(videos: &[Video], timestamp: f32, forward: bool) -> Option<f32> {
+	// Leaves room for rounding, so that the playhead doesn't get stuck on the boundary it's already at
+	const MARGIN: f32 = 0.001;
+
+	let boundaries = videos.iter()
+		.flat_map(|video| [Some(*video.duration.start()), (!video.is_endless()).then(|| video.end())])
+		.flatten();
+
+	if forward {
+		boundaries.filter(|&boundary| boundary > timestamp + MARGIN).reduce(f32::min)
+	} else {
+		boundaries.filter(|&boundary| boundary < timestamp - MARGIN).reduce(f32::max)
+	}
+}
+
+/// Formats a timestamp as `hh:mm:ss:ff`, counting frames at a frame rate, or as `hh:mm:ss` without one
+fn timecode(timestamp: f32, fps: Option<f32>) -> String {
+	let seconds = timestamp as u32;
+	let time = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+	match fps {
+		Some(fps) => format!("{time}:{:02}", (timestamp.fract() * fps) as u32),
+		None => time
+	}
+}
+
+/// Parses a timestamp typed as `[[hh:]mm:]ss[.ms]` into seconds
+fn parse_timestamp(text: &str) -> Option<f32> {
+	let parts: Vec<_> = text.split(':').map(str::parse::<f32>).collect::<Result<_, _>>().ok()?;
+
+	// Only the seconds may have a fraction
+	let (seconds, units) = parts.split_last()?;
+
+	(units.len() <= 2 && units.iter().all(|unit| unit.fract() == 0.0) && *seconds >= 0.0)
+		.then(|| units.iter().fold(0.0, |total, unit| total * 60.0 + unit) * 60.0 + seconds)
+}
+
+/// Returns the frame rate of the topmost [`Video`] on the timeline at a timestamp
+fn active_fps(videos: &[Video], timestamp: f32) -> Option<f32> {
+	draw_order(videos).into_iter().rev().map(|i| &videos[i]).find(|video| video.is_active(timestamp)).map(Video::fps)
+}
+
+/// Rounds a timestamp to the nearest frame boundary of the topmost [`Video`] on the timeline there
+fn snap_to_frame(videos: &[Video], timestamp: f32) -> f32 {
+	active_fps(videos, timestamp).map_or(timestamp, |fps| (timestamp * fps).round() / fps)
+}
+
+/// Lets the user choose whether a high frame rate [`Video`] plays in slow motion or in real time
+fn ask_slow_motion(video: &mut Video) {
+	if video.source.fps > HIGH_FPS {
+		video.slow_motion = MessageDialog::new()
+			.set_level(rfd::MessageLevel::Info)
+			.set_title("High frame rate source")
+			.set_description(format!(
+				"{} runs at {} FPS. Play it as slow motion, showing every frame? Otherwise frames are skipped to play it in real time",
+				video.source.path.display(),
+				video.source.fps
+			))
+			.set_buttons(MessageButtons::YesNo)
+			.show() == MessageDialogResult::Yes;
+	}
+}
+
+/// Tells the user why a file couldn't be added
+fn show_video_error(path: &std::path::Path, error: &VideoError) {
+	MessageDialog::new()
+		.set_level(rfd::MessageLevel::Error)
+		.set_title("Unable to add video")
+		.set_description(format!("{} couldn't be added: {error}", path.display()))
+		.show();
+}
+
+/// Shows a dialog listing the [`Video`]s that failed while decoding, once for each [`Video`] in `reported`
+///
+/// Failed [`Video`]s keep showing a placeholder, so the dialog is only there to explain why
+fn report_failures(videos: &[Video], reported: &mut HashSet<u64>) {
+	let failed: Vec<_> = videos.iter()
+		.filter_map(|video| video.error.as_ref().filter(|_| reported.insert(video.id)).map(|error| format!("{}: {error}", video.source.path.display())))
+		.collect();
+
+	if !failed.is_empty() {
+		MessageDialog::new()
+			.set_level(rfd::MessageLevel::Error)
+			.set_title("Unable to play video")
+			.set_description(format!("Some clips stopped decoding and are shown as placeholders:\n{}", failed.join("\n")))
+			.show();
+	}
+}
+
+/// Replaces the open [`Video`]s with those of the project at `path`, returning it's canvas size if it could be opened
+fn open_project(path: &std::path::Path, videos: &mut Vec<Video>, sources: &mut Sources) -> Option<(u32, u32)> {
+	match Project::load(path) {
+		Ok(project) => {
+			for video in &mut *videos {
+				drop(video.ffmpeg.quit());
+			}
+
+			let failed;
+			(*videos, failed) = project.videos(sources);
+
+			if !failed.is_empty() {
+				let list: Vec<_> = failed.iter().map(|(path, error)| format!("{}: {error}", path.display())).collect();
+
+				MessageDialog::new()
+					.set_level(rfd::MessageLevel::Warning)
+					.set_title("Missing videos")
+					.set_description(format!("Some videos couldn't be loaded and were left out:\n{}", list.join("\n")))
+					.show();
+			}
+
+			Some(project.size)
+		},
+		Err(error) => {
+			MessageDialog::new()
+				.set_level(rfd::MessageLevel::Error)
+				.set_title("Opening failed")
+				.set_description(format!("The project couldn't be opened: {error}"))
+				.show();
+
+			None
+		}
+	}
+}
+
+/// Renders a saved project to a video file without opening a window, for batch jobs
+fn export_headless(project: &std::path::Path, output: &std::path::Path) -> Result<(), String> {
+	let project = Project::load(project).map_err(|error| format!("{} couldn't be opened: {error}", project.display()))?;
+
+	let (mut videos, failed) = project.videos(&mut Sources::default());
+
+	// A render with clips missing is never what was asked for
+	if !failed.is_empty() {
+		for (path, error) in &failed {
+			log::error!("{} couldn't be loaded: {error}", path.display());
+		}
+
+		return Err(format!("{} of the project's videos couldn't be loaded", failed.len()));
+	}
+
+	let range = project_range(&videos);
+	let result = export(output, &mut videos, project.size, range, theme_background(Theme::Dark), &ExportSettings::default());
+
+	for video in &mut videos {
+		drop(video.ffmpeg.quit());
+	}
+
+	result.map_err(|error| format!("{} couldn't be exported: {error}", output.display()))
+}
+
+fn main() -> ExitCode {
+	logger::init();
+
+	let mut settings = Settings::load();
+
+	ffmpeg::set_hwaccel(&settings.hwaccel);
+
+	// `--export <project> <output>` renders a project and exits without showing any windows or dialogs
+	let args: Vec<_> = std::env::args_os().skip(1).collect();
+
+	if args.first().is_some_and(|arg| arg == "--export") {
+		if let Some(path) = settings.ffmpeg_path.filter(|path| ffmpeg::is_valid(path)) {
+			ffmpeg::set_path(path);
+		}
+
+		let [_, project, output] = args.as_slice() else {
+			eprintln!("Usage: dusk --export <project> <output>");
+			return ExitCode::from(2);
+		};
+
+		if let Err(error) = export_headless(project.as_ref(), output.as_ref()) {
+			eprintln!("{error}");
+			return ExitCode::FAILURE;
+		}
+
+		return ExitCode::SUCCESS;
+	}
+
+	if let Some(path) = settings.ffmpeg_path.clone().filter(|path| ffmpeg::is_valid(path)) {
+		ffmpeg::set_path(path);
+	} else if !ffmpeg_is_installed() {
 		MessageDialog::new()
 			.set_level(rfd::MessageLevel::Error)
 			.set_title("FFmpeg not found")
-			.set_description("Please install the latest FFmpeg or place an `ffmpeg` executable adjacent to this program")
+			.set_description("Please install the latest FFmpeg, place an `ffmpeg` executable adjacent to this program or pick one")
 			.show();
 
-		panic!("FFmpeg not found");
+		loop {
+			let Some(path) = FileDialog::new().set_title("Pick FFmpeg executable").pick_file() else {
+				return ExitCode::FAILURE; // Nothing can be done without FFmpeg
+			};
+
+			if ffmpeg::is_valid(&path) {
+				settings.ffmpeg_path = Some(path.clone());
+				settings.save();
+
+				ffmpeg::set_path(path);
+				break;
+			}
+
+			MessageDialog::new()
+				.set_level(rfd::MessageLevel::Error)
+				.set_title("Invalid FFmpeg")
+				.set_description(format!("{} isn't a working FFmpeg executable", path.display()))
+				.show();
+		}
 	}
 
 	if !ffprobe_is_installed() {
@@ -112,7 +534,7 @@ fn main() {
 		panic!("FFprobe not found")
 	}
 
-	let mut background = Color::from_rgba8(25, 25, 35, 255);
+	let mut background = theme_background(Theme::Dark);
 
 	let event_loop = EventLoop::new().unwrap();
 	event_loop.listen_device_events(DeviceEvents::Never);
@@ -142,25 +564,28 @@ fn main() {
 		let w = icon.width();
 		let h = icon.height();
 
-		Rc::new(
-			WindowBuilder::new()
-				.with_title("Dusk")
-				.with_inner_size(LogicalSize::new(1920, 1080))
-				.with_min_inner_size(LogicalSize::new(256, 144))
-				.with_window_icon(Some(Icon::from_rgba(icon.take(), w, h).unwrap()))
-				.build(&event_loop)
-				.unwrap()
-		)
+		let mut builder = WindowBuilder::new()
+			.with_title("Dusk")
+			.with_inner_size(LogicalSize::new(1920, 1080))
+			.with_min_inner_size(LogicalSize::new(256, 144))
+			.with_window_icon(Some(Icon::from_rgba(icon.take(), w, h).unwrap()));
+
+		if let Some(size) = settings.window_size {
+			builder = builder.with_inner_size(size);
+		}
+
+		// Positions on monitors which have since been unplugged or rearranged are dropped, letting the platform place the window
+		if let Some(position) = settings.window_position.filter(|&position| is_on_screen(&event_loop, position)) {
+			builder = builder.with_position(position);
+		}
+
+		Rc::new(builder.build(&event_loop).unwrap())
 	};
 
 	window.theme().map_or_else(
 		|| window.set_theme(Some(Theme::Dark)),
-		|theme| {
-		background = match theme {
-			Theme::Dark => Color::from_rgba8(25, 25, 35, 255),
-			Theme::Light => Color::from_rgba8(225, 225, 235, 255)
-		};
-	});
+		|theme| background = theme_background(theme)
+	);
 
 	let mut surface = {
 		let context = Context::new(window.clone()).unwrap();
@@ -169,16 +594,71 @@ fn main() {
 	};
 
 	let mut videos: Vec<Video> = vec![];
+	let mut history = History::new(settings.history_limit);
+	let mut sources = Sources::default();
+	let mut export_settings = ExportSettings::default();
+	// Export being rendered a few frames at a time in between events
+	let mut exporting: Option<Export> = None;
+	let formats = Formats::query();
+	// Logical cores decoding threads are shared out from
+	let cores = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+	let mut thumbnails = Thumbnails::default();
+	let mut waveforms = Waveforms::default();
+	// File the project was last saved to or opened from
+	let mut project_path: Option<std::path::PathBuf> = None;
+
+	let audio = AudioOutput::new();
+	let mut audio_scrub = AudioScrub::default();
+	let mut playback = Playback::default();
 
 	let mut playhead = 0.0;
 	let mut playing = false;
+	let mut speed = 1.0_f32;
+	let mut loop_region = LoopRegion::default();
+	let mut snap_to_frames = false;
+	// Whether playback starts over from the first clip after the last one ends, rather than pausing
+	let mut loop_project = false;
+	// Timestamp being typed after pressing `g`, shown in the title bar until it's committed with Enter
+	let mut seek_input: Option<String> = None;
+	// Stream URL being typed after pressing Ctrl+U, shown in the title bar until it's added with Enter
+	let mut url_input: Option<String> = None;
+	// Whether the recent files menu is shown in the title bar, waiting for a number to be pressed
+	let mut recent_menu = false;
+	// Last title shown in the title bar, which doubles as a display of the playhead's timecode
+	let mut title = String::new();
+	// Whether the playhead is being dragged along the timeline bar
+	let mut scrubbing = false;
+	// ID of the clip being dragged along the timeline, and the part of it that was grabbed
+	let mut shifting: Option<(u64, ClipGrab)> = None;
+	let mut timeline_view = TimelineView::default();
+	// Whether the timeline is being dragged sideways with the middle mouse button
+	let mut panning = false;
+	// IDs and states of the videos being dragged from before they were grabbed, for recording the move in the history
+	let mut drag_edit: Vec<(u64, VideoState)> = Vec::new();
+	// IDs of the videos which edits apply to, picked by clicking them or dragging a rectangle around them in the timeline
+	let mut selection: HashSet<u64> = HashSet::new();
+	// Whether the timeline was clicked last, in which case the arrow keys nudge the selected clips rather than moving the playhead
+	let mut timeline_focused = false;
+	// States of the videos last copied, which are added again when pasting
+	let mut clipboard: Vec<VideoState> = Vec::new();
+	// Ids of clips whose decoding errors have been shown in a dialog, so that each is only reported once
+	let mut reported_errors: HashSet<u64> = HashSet::new();
+	// Corner of the selection rectangle being dragged in the timeline where the mouse was pressed
+	let mut band: Option<(f32, f32)> = None;
+	// Index into `BACKGROUND_PRESETS` of the canvas color, which follows the theme if `None`
+	let mut background_preset: Option<usize> = None;
+	// Quality frames are decoded at while playing or scrubbing, they're always decoded at full quality when paused
+	let mut decode_quality = DecodeQuality::Full;
 
 	let mut mouse_pos = PhysicalPosition::new(0, 0);
 	let mut mouse_diff = PhysicalPosition::new(0, 0);
+	// Where the clip being dragged on the canvas would be if it didn't snap to the guides
+	let mut drag_position: Option<PhysicalPosition<i32>> = None;
 	let mut mouse_state = ClickState::None;
 	let mut scroll = 0.0_f32;
+	let mut modifiers = ModifiersState::empty();
 
-	let mut gui_enabled = true;
+	let mut gui_enabled = settings.gui_enabled;
 	let mut gui = 0.0_f32;
 
 	let mut size = window.inner_size();
@@ -186,26 +666,275 @@ fn main() {
 	let now = Instant::now();
 	let mut last_elapsed = now.elapsed().as_secs_f32();
 	let mut delta = 0.0_f32;
+	// Step the playhead moves by, negative for moving back, and when the arrow key was pressed, while one is held
+	let mut held_scrub: Option<(f32, Instant)> = None;
+
+	// `--control [port]` lets external controllers drive playback over a socket on localhost
+	#[cfg(feature = "control")]
+	let mut control = args.iter().position(|arg| arg == "--control").and_then(|i| {
+		let port = args.get(i + 1).and_then(|port| port.to_str()?.parse().ok()).unwrap_or(control::DEFAULT_PORT);
+
+		control::ControlServer::start(port)
+			.map_err(|error| log::error!("The control server couldn't be started on port {port}: {error}"))
+			.ok()
+	});
 
 	event_loop.run(move |event, elwt| { match event {
 		Event::AboutToWait => {
-			for video in videos.iter_mut().rev() {
-				if let Some(frame) = &video.frame {
+			#[cfg(feature = "control")]
+			if let Some(control) = &mut control {
+				while let Some(command) = control.poll() {
+					match command {
+						control::Command::Play => playing = true,
+						control::Command::Pause => {
+							playing = false;
+
+							if let Some(audio) = &audio {
+								playback.stop(audio);
+							}
+						},
+						control::Command::Seek { time } => {
+							playhead = time.max(0.0);
+
+							if let (Some(audio), true) = (&audio, playing) {
+								playback.start(audio, &videos, playhead, speed);
+							}
+						},
+						control::Command::Speed { speed: new_speed } => speed = new_speed.signum() * new_speed.abs().clamp(MIN_SPEED, MAX_SPEED)
+					}
+				}
+
+				control.broadcast(playhead, playing, speed);
+			}
+
+			if let Some(mut export) = exporting.take() {
+				let started = Instant::now();
+				let mut result = Ok(true);
+
+				while matches!(result, Ok(true)) && started.elapsed() < EXPORT_BUDGET {
+					result = export.step(&mut videos);
+				}
+
+				match result {
+					Ok(true) => exporting = Some(export),
+					Ok(false) => {
+						let path = export.path().to_path_buf();
+
+						if let Err(error) = export.finish() {
+							show_export_error(&path, &error);
+						}
+					},
+					Err(error) => {
+						show_export_error(export.path(), &error);
+						drop(export.finish());
+					}
+				}
+			}
+
+			let bar = timeline_bar(size.width as f32, size.height as f32, gui);
+
+			if mouse_state == ClickState::Press && bar.is_some_and(|bar| bar.top() <= mouse_pos.y as f32) {
+				scrubbing = true;
+				mouse_state = ClickState::Hold; // Videos below the bar can't be grabbed
+			}
+
+			if scrubbing {
+				if mouse_state == ClickState::None {
+					scrubbing = false;
+
+					if let (Some(audio), true) = (&audio, playing) {
+						playback.start(audio, &videos, playhead, speed);
+					}
+				} else if let Some(bar) = bar {
+					let duration = *project_range(&videos).end();
+					let timestamp = ((mouse_pos.x as f32 - bar.left()) / bar.width()).clamp(0.0, 1.0) * duration;
+					let timestamp = if snap_to_frames { snap_to_frame(&videos, timestamp) } else { timestamp };
+
+					if (timestamp - playhead).abs() > f32::EPSILON {
+						playhead = timestamp;
+
+						if let (Some(audio), false) = (&audio, playing) {
+							audio_scrub.scrub(audio, &videos, playhead);
+						}
+					}
+				}
+			}
+
+			let menu = timeline_menu(size.width as f32, size.height as f32, gui);
+			let line = menu_line(size.width as f32, size.height as f32);
+
+			if mouse_state == ClickState::Press {
+				let (x, y) = (mouse_pos.x as f32, mouse_pos.y as f32);
+
+				timeline_focused = menu.is_some_and(|menu| menu.left() <= x && x <= menu.right() && menu.top() <= y && y <= menu.bottom());
+
+				// Later clips are raised above earlier ones, so they're grabbed first
+				let grabbed = menu.and_then(|menu| videos.iter().enumerate().rev().find_map(|(i, video)| {
+					clip_preview(menu, line, i, video, &timeline_view)
+						.filter(|preview| preview.left() <= x && x <= preview.right() && preview.top() <= y && y <= preview.bottom())
+						.map(|_| {
+							let left = menu.left() + line;
+
+							// The edges of clips scrolled partly out of view can't be grabbed
+							let grab = if x - timeline_view.x(*video.duration.start(), left) <= TRIM_HANDLE {
+								ClipGrab::Head
+							} else if timeline_view.x(video.end(), left) - x <= TRIM_HANDLE {
+								ClipGrab::Tail
+							} else {
+								ClipGrab::Body(timeline_view.time(x, left) - video.duration.start())
+							};
+
+							(video.id, grab, video.state())
+						})
+				}));
+
+				if let Some((id, grab, state)) = grabbed {
+					window.set_cursor_icon(if matches!(grab, ClipGrab::Body(_)) { CursorIcon::Move } else { CursorIcon::EwResize });
+
+					shifting = Some((id, grab));
+					select(&mut selection, id, modifiers);
+
+					// The rest of the selection is moved along with the grabbed clip, but only the grabbed clip is trimmed
+					drag_edit = vec![(id, state)];
+
+					if matches!(grab, ClipGrab::Body(_)) {
+						drag_edit.extend(videos.iter().filter(|video| video.id != id && selection.contains(&video.id)).map(|video| (video.id, video.state())));
+					}
+
+					mouse_state = ClickState::Hold; // Videos beneath the menu can't be grabbed
+				} else if let Some(menu) = menu.filter(|menu| menu.left() <= x && x <= menu.right() && menu.top() <= y && y <= menu.bottom()) {
+					band = Some((x.max(menu.left()), y));
+					mouse_state = ClickState::Hold; // Videos beneath the menu can't be grabbed
+				}
+			}
+
+			if let Some((band_x, band_y)) = band {
+				if mouse_state == ClickState::None {
+					band = None;
+
+					let (x, y) = (mouse_pos.x as f32, mouse_pos.y as f32);
+					let area = Rect::from_ltrb(band_x.min(x), band_y.min(y), band_x.max(x), band_y.max(y));
+
+					// Clicking empty space in the timeline without dragging clears the selection
+					if !modifiers.shift_key() {
+						selection.clear();
+					}
+
+					if let (Some(menu), Some(area)) = (menu, area) {
+						selection.extend(videos.iter().enumerate()
+							.filter(|(i, video)| clip_preview(menu, line, *i, video, &timeline_view).is_some_and(|preview| preview.intersect(&area).is_some()))
+							.map(|(_, video)| video.id)
+						);
+					}
+				}
+			}
+
+			if let Some((id, grab)) = shifting {
+				if mouse_state == ClickState::None {
+					shifting = None;
+
+					if let (Some(audio), true) = (&audio, playing) {
+						playback.start(audio, &videos, playhead, speed);
+					}
+				} else if let Some(menu) = menu {
+					let time = timeline_view.time(mouse_pos.x as f32, menu.left() + line).max(0.0);
+
+					// Holding Alt places clips freely
+					let targets: Vec<_> = if modifiers.alt_key() {
+						Vec::new()
+					} else {
+						videos.iter()
+							.filter(|video| !drag_edit.iter().any(|(dragged, _)| *dragged == video.id))
+							.flat_map(|video| [*video.duration.start(), video.end()])
+							.chain([playhead])
+							.collect()
+					};
+
+					let threshold = timeline_view.seconds(SNAP_DISTANCE);
+
+					// Clips moved along with the grabbed one keep their distance to it, but none of them can be moved before the start of the project
+					let earliest = videos.iter()
+						.filter(|video| video.id != id && drag_edit.iter().any(|(dragged, _)| *dragged == video.id))
+						.map(|video| *video.duration.start())
+						.fold(f32::INFINITY, f32::min);
+
+					if let Some(video) = videos.iter_mut().find(|video| video.id == id) {
+						match grab {
+							ClipGrab::Body(offset) => {
+								let before = *video.duration.start();
+								let length = video.end() - before;
+								video.move_to(snap_clip((time - offset).max(0.0), length, &targets, threshold).max(before - earliest.min(before)));
+
+								let moved = video.duration.start() - before;
+
+								for video in &mut videos {
+									if video.id != id && drag_edit.iter().any(|(dragged, _)| *dragged == video.id) {
+										video.move_to(video.duration.start() + moved);
+									}
+								}
+							},
+							ClipGrab::Head => video.trim_head(snap_clip(time, 0.0, &targets, threshold)),
+							ClipGrab::Tail => video.trim_tail(snap_clip(time, 0.0, &targets, threshold))
+						}
+					}
+				}
+			}
+
+			let quality = if playing || scrubbing { decode_quality } else { DecodeQuality::Full };
+			// Seeks only have to land on the right frame once scrubbing stops
+			let seek_mode = if scrubbing || held_scrub.is_some() { SeekMode::Fast } else { SeekMode::Exact };
+
+			// The cores are shared between the clips being decoded, so that many clips at once don't oversubscribe the CPU
+			let threads = settings.decoder_threads.unwrap_or_else(|| {
+				let decoding = videos.iter().filter(|video| video.is_active(playhead)).count().clamp(1, settings.max_decoders);
+				(cores / decoding).max(1)
+			});
+
+			// Clips drawn on top are grabbed first
+			for i in draw_order(&videos).into_iter().rev() {
+				let video = &mut videos[i];
+
+				// Exports decode at the size they render at until they're done
+				if exporting.is_none() {
+					video.set_render_scale(1.0);
+				}
+
+				video.set_quality(quality);
+				video.set_seek_mode(seek_mode);
+				video.set_threads(threads);
+				video.set_playback_speed(playing.then_some(speed));
+
+				if video.frame.is_some() {
 					if mouse_state == ClickState::None {
 						video.drag = Drag::None;
+						drag_position = None;
 					} else if mouse_state == ClickState::Press {
-						let half_width = frame.width() as i32 / 2;
-						let half_height = frame.height() as i32 / 2;
-
-						if (video.x + half_width - mouse_pos.x).abs() < half_width && (video.y + half_height - mouse_pos.y).abs() < half_height {
+						if video.contains(mouse_pos.x, mouse_pos.y) {
 							window.set_cursor_icon(CursorIcon::Move);
 							mouse_state = ClickState::Hold; // No other videos later in the video array can be grabbed
 
 							video.drag = Drag::Move;
+							drag_position = None;
+							select(&mut selection, video.id, modifiers);
+							drag_edit = vec![(video.id, video.state())];
 						}
 					} else if video.drag == Drag::Move {
-						video.x += mouse_diff.x;
-						video.y += mouse_diff.y;
+						if mouse_diff != PhysicalPosition::new(0, 0) || scroll.abs() > 0.001 {
+							video.scale_mode = None; // Moved or resized by hand from now on
+						}
+
+						let position = drag_position.get_or_insert_with(|| PhysicalPosition::new(video.x, video.y));
+						position.x += mouse_diff.x;
+						position.y += mouse_diff.y;
+
+						// Holding Alt places the clip freely
+						(video.x, video.y) = match video.size() {
+							Some((width, height)) if !modifiers.alt_key() => (
+								snap_to_guides(position.x, width, size.width),
+								snap_to_guides(position.y, height, size.height)
+							),
+							_ => (position.x, position.y)
+						};
 
 						mouse_diff = PhysicalPosition::new(0, 0);
 
@@ -225,7 +954,33 @@ fn main() {
 				}
 			}
 
+			// Moves are recorded once the video has been released and has finished resizing
+			let resizing = videos.iter().any(|video| video.scale.is_some() && drag_edit.iter().any(|(id, _)| *id == video.id));
+
+			if !drag_edit.is_empty() && mouse_state == ClickState::None && !resizing {
+				// Clips moved together are undone in one step
+				let changes = std::mem::take(&mut drag_edit).into_iter()
+					.filter_map(|(id, before)| videos.iter_mut().find(|video| video.id == id).map(|video| {
+						// Animated clips keep where they were moved or resized to from the playhead on
+						if (video.x, video.y, video.state().size) != (before.x, before.y, before.size) {
+							video.update_keyframe(playhead);
+						}
+
+						(id, before, video.state())
+					}))
+					.filter(|(_, before, after)| before != after)
+					.map(|(id, before, after)| EditCommand::Change { id, before, after })
+					.collect();
+
+				history.push_all(changes);
+			}
+
 			if mouse_state == ClickState::Press {
+				// Clicked beside every video
+				if !modifiers.shift_key() {
+					selection.clear();
+				}
+
 				mouse_state = ClickState::Hold;
 			}
 
@@ -243,10 +998,28 @@ fn main() {
 			//println!("scroll: {scroll}");
 			//println!("timeline: {timeline}");
 
+			// Redrawing faster than the monitor can show only wastes time decoding and compositing frames nobody sees, but exports aren't held back
+			let fps_limit = settings.preview_fps_limit.or_else(|| window.current_monitor()
+				.and_then(|monitor| monitor.refresh_rate_millihertz())
+				.map(|millihertz| millihertz as f32 / 1000.0)
+			).filter(|_| exporting.is_none());
+
+			let next_frame = fps_limit.map_or(now, |fps| now + Duration::from_secs_f32(last_elapsed + fps.recip()));
+
 			// && (playing || scroll.abs() > 0.001 || (timeline > 0.001 && timeline < 0.999))
-			if visible && !minimized {
+			if visible && !minimized && Instant::now() >= next_frame {
 				window.request_redraw();
 			}
+
+			elwt.set_control_flow(if exporting.is_some() {
+				// Exports keep going while the window is hidden or minimized, when nothing else wakes it up
+				ControlFlow::Poll
+			} else if visible && !minimized {
+				ControlFlow::WaitUntil(next_frame)
+			} else {
+				// Nothing is drawn while the window can't be seen, so it waits to be shown again instead of spinning on a deadline that has passed
+				ControlFlow::Wait
+			});
 		},
 		Event::WindowEvent { event, .. } => match event {
 			WindowEvent::RedrawRequested => {
@@ -255,7 +1028,103 @@ fn main() {
 				last_elapsed = new_elapsed;
 				//println!("{}", delta.recip());
 	
-				if playing { playhead += delta; }
+				if let Some((step, since)) = held_scrub {
+					let held = since.elapsed().as_secs_f32() - SCRUB_HOLD_DELAY;
+
+					if held > 0.0 {
+						let rate = step * HELD_SCRUB_RATE * held.exp2().min(MAX_SCRUB_ACCELERATION);
+						playhead = rate.mul_add(delta, playhead).max(0.0);
+
+						if let (Some(audio), false) = (&audio, playing) {
+							audio_scrub.scrub(audio, &videos, playhead);
+						}
+					}
+				}
+
+				if playing {
+					playhead = delta.mul_add(speed, playhead).max(0.0);
+
+					let end = project_end(&videos).filter(|&end| speed > 0.0 && playhead > end);
+
+					if let Some((wrapped, new_speed)) = loop_region.wrap(playhead, speed) {
+						playhead = wrapped;
+						speed = new_speed;
+
+						if let Some(audio) = &audio {
+							playback.start(audio, &videos, playhead, speed);
+						}
+					} else if let Some(end) = end {
+						// Playing past the last clip either starts over or stops at it's end
+						if loop_project {
+							playhead = *project_range(&videos).start();
+
+							if let Some(audio) = &audio {
+								playback.start(audio, &videos, playhead, speed);
+							}
+						} else {
+							playing = false;
+							playhead = end;
+
+							if let Some(audio) = &audio {
+								playback.stop(audio);
+							}
+						}
+					}
+
+					if let Some(audio) = &audio {
+						playback.update(audio, &videos, playhead, speed);
+					}
+				}
+
+				let new_title = exporting.as_ref().map_or_else(|| if recent_menu {
+					let entries: Vec<_> = settings.recent()
+						.zip((1..=9).chain([0]))
+						.map(|(path, i)| format!("{i} {}", path.file_name().unwrap_or(path.as_os_str()).to_string_lossy()))
+						.collect();
+
+					if entries.is_empty() {
+						String::from("Dusk - No recent files - Esc to close")
+					} else {
+						format!("Dusk - Recent: {}", entries.join(" | "))
+					}
+				} else if let Some(input) = &url_input {
+					format!("Dusk - Open URL {input}")
+				} else { seek_input.as_ref().map_or_else(|| {
+					// Frames are counted at the rate of the topmost clip under the playhead
+					let fps = active_fps(&videos, playhead);
+
+					// Unsaved changes are marked with a `*`, like in other editors
+					let project = project_path.as_ref()
+						.and_then(|path| path.file_stem())
+						.map_or_else(|| String::from("Untitled"), |name| name.to_string_lossy().into_owned());
+
+					// Debug builds show how far the audio is from the playhead, to check that it stays in sync
+					let drift = if playing && audio.is_some() && cfg!(debug_assertions) {
+						format!(" - A/V drift {:+.0} ms", playback.drift * 1000.0)
+					} else {
+						String::new()
+					};
+
+					format!(
+						"Dusk - {project}{} - {} - {} - {}{drift}",
+						if history.is_dirty() { "*" } else { "" },
+						timecode(playhead, fps),
+						fps.map_or_else(|| String::from("No clip"), |fps| format!("{fps:.2} FPS")),
+						if playing { "Playing" } else { "Paused" }
+					)
+				}, |input| format!("Dusk - Go to {input}")) }, |export| {
+					let remaining = export.remaining().map_or_else(String::new, |remaining| {
+						let seconds = remaining.as_secs();
+						format!(" - {}:{:02} left", seconds / 60, seconds % 60)
+					});
+
+					format!("Dusk - Exporting {:.0}%{remaining} - Esc to cancel", export.progress() * 100.0)
+				});
+
+				if new_title != title {
+					window.set_title(&new_title);
+					title = new_title;
+				}
 	
 				let mut buffer = surface.buffer_mut().unwrap();
 
@@ -265,28 +1134,124 @@ fn main() {
 					size.height
 				).unwrap();
 
-				render_frame(&mut pixmap, &mut videos, playhead, background);
+				// Playback keeps advancing smoothly, only the frame shown is snapped
+				let time = if snap_to_frames { snap_to_frame(&videos, playhead) } else { playhead };
+
+				// Exports show the frames they render instead, since the preview would make the decoders seek back and forth
+				if let Some(export) = &exporting {
+					let frame = export.frame();
+
+					// Frames are fit into the window without stretching them, like the project is fit into them
+					let scale = (size.width as f32 / frame.width() as f32).min(size.height as f32 / frame.height() as f32);
+
+					pixmap.fill(canvas_color(background, background_preset));
+					pixmap.draw_pixmap(
+						0,
+						0,
+						frame.as_ref(),
+						&PixmapPaint {
+							blend_mode: BlendMode::Source,
+							..PixmapPaint::default()
+						},
+						Transform::from_scale(scale, scale).post_translate(
+							((frame.width() as f32).mul_add(-scale, size.width as f32) / 2.0).round(),
+							((frame.height() as f32).mul_add(-scale, size.height as f32) / 2.0).round()
+						),
+						None
+					);
+				} else {
+					render_frame(&mut pixmap, &mut videos, time, canvas_color(background, background_preset), Transform::identity(), settings.scale_quality, settings.max_decoders);
+					report_failures(&videos, &mut reported_errors);
+				}
+
+				if gui > 0.001 && !modifiers.alt_key() && videos.iter().any(|video| video.drag == Drag::Move) {
+					// Only the thirds and center are drawn, since the edges are already visible
+					let mut guide_lines = PathBuilder::new();
+
+					for x in &guides(size.width)[1..4] {
+						guide_lines.move_to(*x as f32, 0.0);
+						guide_lines.line_to(*x as f32, size.height as f32);
+					}
+
+					for y in &guides(size.height)[1..4] {
+						guide_lines.move_to(0.0, *y as f32);
+						guide_lines.line_to(size.width as f32, *y as f32);
+					}
+
+					if let Some(guide_lines) = guide_lines.finish() {
+						pixmap.stroke_path(
+							&guide_lines,
+							&Paint {
+								shader: Shader::SolidColor(Color::from_rgba8(255, 255, 255, (gui * 96.0) as u8)),
+								..Paint::default()
+							},
+							&Stroke::default(),
+							Transform::identity(),
+							None
+						);
+					}
+				}
+
+				if gui > 0.001 {
+					let outlines = videos.iter()
+						.filter(|video| selection.contains(&video.id))
+						.filter_map(|video| video.size().and_then(|(width, height)| Rect::from_xywh(video.x as f32, video.y as f32, width as f32, height as f32)));
+
+					for outline in outlines {
+						pixmap.stroke_path(
+							&PathBuilder::from_rect(outline),
+							&Paint {
+								shader: Shader::SolidColor(Color::from_rgba8(255, 134, 4, (gui * 255.0) as u8)),
+								..Paint::default()
+							},
+							&Stroke {
+								width: 2.0,
+								..Default::default()
+							},
+							Transform::identity(),
+							None
+						);
+					}
+				}
 
 				if gui > 0.001 {
 					let scr_w = pixmap.width() as f32;
 					let scr_h = pixmap.height() as f32;
+
+					if let Some(bar) = timeline_bar(scr_w, scr_h, gui) {
+						let alpha = 10.0 * gui.min(0.1);
+						let duration = *project_range(&videos).end();
+
+						pixmap.fill_rect(
+							bar,
+							&Paint {
+								shader: Shader::SolidColor(Color::from_rgba8(35, 35, 55, (alpha * 200.0) as u8)),
+								..Paint::default()
+							},
+							Transform::identity(),
+							None
+						);
+
+						let progress = (duration > 0.0).then(|| bar.width() * (playhead / duration).min(1.0))
+							.and_then(|width| Rect::from_xywh(bar.left(), bar.top(), width, bar.height()));
+
+						if let Some(progress) = progress {
+							pixmap.fill_rect(
+								progress,
+								&Paint {
+									shader: Shader::SolidColor(Color::from_rgba8(173, 216, 230, (alpha * 200.0) as u8)),
+									..Paint::default()
+								},
+								Transform::identity(),
+								None
+							);
+						}
+					}
 	
-					let menu = {
-						let w = scr_w * (gui - 0.5).max(0.0).mul_add(1.5, 0.05);
-						let h = scr_h * gui.min(0.5) * 0.15;
-		
-						Rect::from_xywh(
-							scr_w.mul_add(0.5, w * -0.5),
-							scr_h.mul_add(-0.015 * gui, scr_h - h),
-							w,
-							h
-						)
-					};
-		
-					if let Some(menu) = menu {
+					if let Some(menu) = timeline_menu(scr_w, scr_h, gui) {
 						let alpha = 10.0 * gui.min(0.1);
 	
-						let line = scr_w.min(scr_h) * 0.0025;
+						let line = menu_line(scr_w, scr_h);
 	
 						stroke_fill_path(
 							&mut pixmap,
@@ -306,24 +1271,30 @@ fn main() {
 							}
 						);
 	
-						let zoom = 10.0;
+						let left = menu.left() + line;
+						let zoom = timeline_view.pixels_per_second;
+
+						let region = loop_region.range().and_then(|range| Rect::from_ltrb(
+							timeline_view.x(*range.start(), left).clamp(left, menu.right() - line),
+							menu.top() + line,
+							timeline_view.x(*range.end(), left).clamp(left, menu.right() - line),
+							menu.bottom() - line
+						));
+
+						if let Some(region) = region {
+							pixmap.fill_rect(
+								region,
+								&Paint {
+									shader: Shader::SolidColor(Color::from_rgba8(255, 134, 4, (alpha * 60.0) as u8)),
+									..Paint::default()
+								},
+								Transform::identity(),
+								None
+							);
+						}
 	
 						for (i, video) in videos.iter().enumerate() {
-							let preview = {
-								let l = menu.left() + line;
-								let t = (i as f32).mul_add(-5.0, menu.top() + line);
-								let r = menu.right() - line;
-								let b = (i as f32).mul_add(-5.0, menu.bottom() - line);
-	
-								Rect::from_ltrb(
-									(l + video.duration.start() * zoom).min(r),
-									t,
-									(l + video.duration.end() * zoom).min(r),
-									b
-								)
-							};
-	
-							if let Some(preview) = preview {
+							if let Some(preview) = clip_preview(menu, line, i, video, &timeline_view) {
 								stroke_fill_path(
 									&mut pixmap,
 									&PathBuilder::from_rect(preview),
@@ -332,7 +1303,11 @@ fn main() {
 										..Paint::default()
 									},
 									&Paint {
-										shader: Shader::SolidColor(Color::from_rgba8(35, 35, 55, (alpha * 100.0) as u8)),
+										shader: Shader::SolidColor(if selection.contains(&video.id) {
+											Color::from_rgba8(255, 134, 4, (alpha * 255.0) as u8)
+										} else {
+											Color::from_rgba8(35, 35, 55, (alpha * 100.0) as u8)
+										}),
 										..Paint::default()
 									},
 									&Stroke {
@@ -341,11 +1316,142 @@ fn main() {
 										..Default::default()
 									}
 								);
+
+								// Fill the part of the clip covered by it's source with a filmstrip
+								if let Some(length) = video.length() {
+									let scale = preview.height() / thumbnails::HEIGHT as f32;
+									// Based on the decoded size rather than the drawn one, which changes while the GUI is animating
+									let thumbnail_width = thumbnails::HEIGHT as f32 * video.source.width.get() as f32 / video.source.height.get() as f32;
+									let count = (length * zoom / thumbnail_width).ceil().clamp(1.0, 32.0) as usize;
+
+									if let Some(strip) = thumbnails.get(&video.source, count) {
+										for (i, thumbnail) in strip.iter().enumerate() {
+											// The source starts before the clip if it's head has been cut off, and the strip may have a different count than requested
+											let x = timeline_view.x((i as f32).mul_add(length / strip.len() as f32, video.shift), left);
+
+											if x >= preview.right() {
+												break;
+											}
+
+											if (thumbnail.width() as f32).mul_add(scale, x) <= preview.left() {
+												continue;
+											}
+
+											pixmap.draw_pixmap(
+												0,
+												0,
+												thumbnail.as_ref(),
+												&PixmapPaint {
+													opacity: alpha * 0.75,
+													..PixmapPaint::default()
+												},
+												Transform::from_row(scale, 0.0, 0.0, scale, x, preview.top()),
+												None
+											);
+										}
+									}
+								}
+
+								// Trace the loudness of the clip's audio along the middle of it
+								if let Some(waveform) = waveforms.get(&video.source) {
+									let mut path = PathBuilder::new();
+									let middle = preview.height().mul_add(0.5, preview.top());
+
+									for column in 0..preview.width() as u32 {
+										let time = timeline_view.time(preview.left() + column as f32, left);
+										let peak = (video.source_time(time) * waveforms::PEAKS_PER_SECOND as f32) as usize;
+
+										let bar = waveform.get(peak).and_then(|&(min, max)| Rect::from_xywh(
+											preview.left() + column as f32,
+											max.mul_add(-preview.height() * 0.5, middle),
+											1.0,
+											(max - min).mul_add(preview.height() * 0.5, 1.0)
+										));
+
+										if let Some(bar) = bar {
+											path.push_rect(bar);
+										}
+									}
+
+									if let Some(path) = path.finish() {
+										pixmap.fill_path(
+											&path,
+											&Paint {
+												shader: Shader::SolidColor(Color::from_rgba8(35, 35, 55, (alpha * 150.0) as u8)),
+												..Paint::default()
+											},
+											FillRule::default(),
+											Transform::identity(),
+											None
+										);
+									}
+								}
+
+								// Mark the part of the clip that extends beyond it's source
+								let overrun = video.source_end().and_then(|end| Rect::from_ltrb(
+									timeline_view.x(end, left).clamp(preview.left(), preview.right()),
+									preview.top(),
+									preview.right(),
+									preview.bottom()
+								));
+
+								if let Some(overrun) = overrun {
+									pixmap.fill_rect(
+										overrun,
+										&Paint {
+											shader: Shader::SolidColor(Color::from_rgba8(230, 80, 60, (alpha * 125.0) as u8)),
+											..Paint::default()
+										},
+										Transform::identity(),
+										None
+									);
+								}
 							}
 						}
+
+						let band = band.and_then(|(band_x, band_y)| {
+							let (x, y) = (mouse_pos.x as f32, mouse_pos.y as f32);
+							Rect::from_ltrb(band_x.min(x), band_y.min(y), band_x.max(x), band_y.max(y))
+						});
+
+						if let Some(band) = band {
+							stroke_fill_path(
+								&mut pixmap,
+								&PathBuilder::from_rect(band),
+								&Paint {
+									shader: Shader::SolidColor(Color::from_rgba8(255, 134, 4, (alpha * 40.0) as u8)),
+									..Paint::default()
+								},
+								&Paint {
+									shader: Shader::SolidColor(Color::from_rgba8(255, 134, 4, (alpha * 200.0) as u8)),
+									..Paint::default()
+								},
+								&Stroke {
+									width: line * 0.5,
+									..Default::default()
+								}
+							);
+						}
 					}
 				}
 	
+				if let Some(export) = &exporting {
+					let progress = timeline_bar(size.width as f32, size.height as f32, 1.0)
+						.and_then(|bar| Rect::from_xywh(bar.left(), bar.top(), bar.width() * export.progress(), bar.height()));
+
+					if let Some(progress) = progress {
+						pixmap.fill_rect(
+							progress,
+							&Paint {
+								shader: Shader::SolidColor(Color::from_rgba8(255, 134, 4, 255)),
+								..Paint::default()
+							},
+							Transform::identity(),
+							None
+						);
+					}
+				}
+
 				parallel!(for pix in pixmap.pixels_mut() {
 					*pix = PremultipliedColorU8::from_rgba(pix.blue(), pix.green(), pix.red(), ALPHA_U8_OPAQUE).unwrap();
 				});
@@ -353,6 +1459,8 @@ fn main() {
 				window.pre_present_notify();
 				buffer.present().unwrap();
 			},
+			WindowEvent::MouseInput { .. } | WindowEvent::DroppedFile(_) if exporting.is_some() => (), // The project can't be edited while it's being exported
+			WindowEvent::MouseInput { button: MouseButton::Middle, state, .. } => panning = state == ElementState::Pressed,
 			WindowEvent::MouseInput { state, .. } => mouse_state = match state {
 				ElementState::Pressed => ClickState::Press,
 				ElementState::Released => {
@@ -360,16 +1468,182 @@ fn main() {
 					ClickState::None
 				}
 			},
+			WindowEvent::ModifiersChanged(new_modifiers) => modifiers = new_modifiers.state(),
+			WindowEvent::Focused(false) => held_scrub = None, // The arrow key's release won't arrive
 			WindowEvent::CursorMoved { position, .. } => {
 				if mouse_state == ClickState::Hold {
 					mouse_diff.x += position.x as i32 - mouse_pos.x;
 					mouse_diff.y += position.y as i32 - mouse_pos.y;
 				}
 
+				if panning {
+					timeline_view.pan(position.x as f32 - mouse_pos.x as f32);
+				}
+
 				mouse_pos.x = position.x as i32;
 				mouse_pos.y = position.y as i32;
 			},
-			WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(_, y), .. } => scroll -= y * 0.0125,
+			// Scrolling over the timeline zooms it rather than resizing the grabbed video
+			WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(_, y), .. } => match timeline_menu(size.width as f32, size.height as f32, gui) {
+				Some(menu) if menu.left() <= mouse_pos.x as f32 && mouse_pos.x as f32 <= menu.right() && menu.top() <= mouse_pos.y as f32 && mouse_pos.y as f32 <= menu.bottom() => {
+					timeline_view.zoom_at(1.25_f32.powf(y), mouse_pos.x as f32, menu.left() + menu_line(size.width as f32, size.height as f32));
+				},
+				_ => scroll -= y * 0.0125
+			},
+			WindowEvent::KeyboardInput {
+				event: KeyEvent {
+					logical_key: Key::Named(NamedKey::Escape),
+					state: ElementState::Pressed,
+					..
+				},
+				..
+			} if exporting.is_some() => {
+				if let Some(export) = exporting.take() {
+					let path = export.path().to_path_buf();
+
+					// Half written files are never left behind without telling the user
+					let description = match export.cancel() {
+						Ok(()) => format!("The unfinished {} was removed", path.display()),
+						Err(error) => format!("The unfinished {} couldn't be removed: {error}", path.display())
+					};
+
+					MessageDialog::new()
+						.set_level(rfd::MessageLevel::Info)
+						.set_title("Export cancelled")
+						.set_description(description)
+						.show();
+				}
+			},
+			WindowEvent::KeyboardInput { .. } if exporting.is_some() => (),
+			// Playback continues from wherever holding an arrow key moved the playhead to
+			WindowEvent::KeyboardInput {
+				event: KeyEvent {
+					logical_key: Key::Named(NamedKey::ArrowLeft | NamedKey::ArrowRight),
+					state: ElementState::Released,
+					..
+				},
+				..
+			} if held_scrub.is_some() => {
+				held_scrub = None;
+
+				if snap_to_frames {
+					playhead = snap_to_frame(&videos, playhead);
+				}
+
+				if let (Some(audio), true) = (&audio, playing) {
+					playback.start(audio, &videos, playhead, speed);
+				}
+			},
+			WindowEvent::KeyboardInput {
+				event: KeyEvent {
+					logical_key: key,
+					state: ElementState::Pressed,
+					..
+				},
+				..
+			} if seek_input.is_some() => {
+				let input = seek_input.as_mut().unwrap();
+
+				match key {
+					Key::Named(NamedKey::Enter) => {
+						// Invalid timestamps are dropped without moving the playhead
+						if let Some(timestamp) = parse_timestamp(input) {
+							playhead = if snap_to_frames {
+								snap_to_frame(&videos, timestamp)
+							} else {
+								timestamp
+							};
+
+							if let Some(audio) = &audio {
+								if playing {
+									playback.start(audio, &videos, playhead, speed);
+								} else {
+									audio_scrub.scrub(audio, &videos, playhead);
+								}
+							}
+						}
+
+						seek_input = None;
+					},
+					Key::Named(NamedKey::Escape) => seek_input = None,
+					Key::Named(NamedKey::Backspace) => drop(input.pop()),
+					Key::Character(text) if text.chars().all(|char| char.is_ascii_digit() || char == ':' || char == '.') => input.push_str(&text),
+					_ => ()
+				}
+			},
+			WindowEvent::KeyboardInput {
+				event: KeyEvent {
+					logical_key: key,
+					state: ElementState::Pressed,
+					..
+				},
+				..
+			} if url_input.is_some() => {
+				let input = url_input.as_mut().unwrap();
+
+				match key {
+					Key::Named(NamedKey::Enter) => {
+						let path = std::path::Path::new(input.trim());
+
+						// Anything that isn't a URL would be read as a local file, which Import is for
+						if is_url(path) {
+							if let Some(video) = add_video(path, &videos, &selection, &settings, playhead, &mut sources) {
+								history.push(EditCommand::add(&video));
+								videos.push(video);
+							}
+						} else if !path.as_os_str().is_empty() {
+							log::warn!("{} isn't a URL, so it wasn't opened", path.display());
+						}
+
+						url_input = None;
+					},
+					Key::Named(NamedKey::Escape) => url_input = None,
+					Key::Named(NamedKey::Backspace) => drop(input.pop()),
+					Key::Character(text) if !modifiers.control_key() => input.push_str(&text),
+					_ => ()
+				}
+			},
+			WindowEvent::KeyboardInput {
+				event: KeyEvent {
+					logical_key: key,
+					state: ElementState::Pressed,
+					..
+				},
+				..
+			} if recent_menu => {
+				// Entries are numbered 1 through 9 followed by 0, like the number row
+				let entry = match &key {
+					Key::Character(text) => text.parse::<usize>().ok().map(|i| (i + 9) % 10).and_then(|i| settings.recent().nth(i).cloned()),
+					_ => None
+				};
+
+				if let Some(path) = entry {
+					recent_menu = false;
+
+					if path.extension().is_some_and(|extension| extension == project::EXTENSION) {
+						if let Some((width, height)) = open_project(&path, &mut videos, &mut sources) {
+							if let Some(audio) = &audio {
+								playback.stop(audio);
+							}
+
+							history = History::new(settings.history_limit);
+							settings.remember_project(&path);
+							project_path = Some(path);
+
+							// The surface is resized once the `Resized` event arrives
+							drop(window.request_inner_size(PhysicalSize::new(width, height)));
+						}
+					} else if let Some(video) = add_video(&path, &videos, &selection, &settings, playhead, &mut sources) {
+						history.push(EditCommand::add(&video));
+						videos.push(video);
+						settings.remember_video(&path);
+					}
+
+					settings.save();
+				} else if key == Key::Named(NamedKey::Escape) {
+					recent_menu = false;
+				}
+			},
 			WindowEvent::KeyboardInput {
 				event: KeyEvent {
 					logical_key: key,
@@ -380,10 +1654,71 @@ fn main() {
 				..
 			} => match key {
 				Key::Named(key) => match key {
-					NamedKey::Space => playing = !playing,
+					NamedKey::Space => {
+						playing = !playing;
+						log::debug!("{} at {playhead:.3}s", if playing { "Playing" } else { "Paused" });
+
+						if let (Some(audio), false) = (&audio, playing) {
+							playback.stop(audio);
+						}
+					},
 					NamedKey::Tab => gui_enabled = !gui_enabled,
-					NamedKey::ArrowLeft => playhead = (playhead - 5.0).max(0.0),
-					NamedKey::ArrowRight => playhead += 1.0,
+					// Shift nudges the selected clips by a pixel on the canvas, and otherwise by one of their frames along the timeline
+					NamedKey::ArrowLeft | NamedKey::ArrowRight | NamedKey::ArrowUp | NamedKey::ArrowDown if timeline_focused && !selection.is_empty() && modifiers.shift_key() => {
+						let (x, y) = match key {
+							NamedKey::ArrowLeft => (-1, 0),
+							NamedKey::ArrowRight => (1, 0),
+							NamedKey::ArrowUp => (0, -1),
+							_ => (0, 1)
+						};
+
+						history.edit_all(videos.iter_mut().filter(|video| selection.contains(&video.id)), |video| {
+							video.x += x;
+							video.y += y;
+							video.scale_mode = None; // Moved by hand from now on
+							video.update_keyframe(playhead);
+						});
+					},
+					NamedKey::ArrowLeft | NamedKey::ArrowRight if timeline_focused && !selection.is_empty() => {
+						let direction = if key == NamedKey::ArrowLeft { -1.0_f32 } else { 1.0 };
+
+						history.edit_all(videos.iter_mut().filter(|video| selection.contains(&video.id)), |video| {
+							let frame = video.fps().recip();
+							video.move_to(direction.mul_add(frame, *video.duration.start()).max(0.0));
+						});
+					},
+					// Ctrl moves the playhead by a smaller step
+					NamedKey::ArrowLeft | NamedKey::ArrowRight => {
+						let step = if modifiers.control_key() { settings.scrub.fine } else { settings.scrub.coarse };
+
+						let step = if key == NamedKey::ArrowLeft { -step } else { step };
+
+						playhead = (playhead + step).max(0.0);
+						held_scrub = Some((step, Instant::now()));
+
+						if snap_to_frames {
+							playhead = snap_to_frame(&videos, playhead);
+						}
+
+						if let Some(audio) = &audio {
+							if playing {
+								playback.start(audio, &videos, playhead, speed);
+							} else {
+								audio_scrub.scrub(audio, &videos, playhead);
+							}
+						}
+					},
+					NamedKey::PageUp | NamedKey::PageDown => if let Some(boundary) = clip_boundary(&videos, playhead, key == NamedKey::PageDown) {
+						playhead = boundary;
+
+						if let Some(audio) = &audio {
+							if playing {
+								playback.start(audio, &videos, playhead, speed);
+							} else {
+								audio_scrub.scrub(audio, &videos, playhead);
+							}
+						}
+					},
 					NamedKey::ArrowUp => scroll -= 0.005,
 					NamedKey::ArrowDown => scroll += 0.005,
 					NamedKey::F11 => window.set_fullscreen(
@@ -393,15 +1728,44 @@ fn main() {
 							None
 						}
 					),
-					NamedKey::Delete => videos.retain_mut(|video| {
-						let keep = video.drag == Drag::None;
+					// Saves the frame under the playhead as an image, without the GUI drawn over the preview
+					NamedKey::F12 => {
+						window.set_visible(false);
+
+						let file = FileDialog::new()
+							.set_file_name("dusk-frame.png")
+							.set_directory(current_dir().unwrap())
+							.add_filter("PNG image", &["png"])
+							.set_title("Save frame")
+							.save_file();
+
+						window.set_visible(true);
 
-						if !keep {
-							drop(video.ffmpeg.quit());
+						if let Some(file) = file {
+							if let Err(error) = export_frame(&file, &mut videos, (size.width, size.height), playhead, canvas_color(background, background_preset)) {
+								show_export_error(&file, &error);
+							}
 						}
+					},
+					// Every selected clip is removed in one step, and holding Shift closes the gaps left on their tracks
+					NamedKey::Delete => {
+						let mut removed = Vec::new();
+
+						for id in selection.drain() {
+							let Some(i) = videos.iter().position(|video| video.id == id) else { continue };
 
-						keep
-					}),
+							if modifiers.shift_key() {
+								removed.push(ripple_remove(&mut videos, i));
+							} else {
+								removed.push(EditCommand::remove(&videos[i], i));
+
+								// `FFmpeg` is quit before the video is dropped so that it doesn't linger
+								drop(videos.remove(i).ffmpeg.quit());
+							}
+						}
+
+						history.push_all(removed);
+					},
 					_ => ()
 				},
 				Key::Character(key) => match key.as_str() {
@@ -411,22 +1775,317 @@ fn main() {
 						let path = current_dir().unwrap();
 
 						let res = FileDialog::new()
-							.add_filter("Video", VIDEO_EXTENSIONS)
-							.add_filter("Image", IMAGE_EXTENSIONS)
+							.add_filter("Video", &formats.video)
+							.add_filter("Image", &formats.image)
 							.set_directory(path)
 							.set_title("Import")
 							.pick_files();
 
 						if let Some(files) = res {
+							let mut added = Vec::new();
+
+							// Appended files follow each other in the order they were picked
 							for file in files {
-								if let Some(video) = Video::new(file, playhead) {
+								if let Some(video) = add_video(&file, &videos, &selection, &settings, playhead, &mut sources) {
+									settings.remember_video(&file);
+									added.push(EditCommand::add(&video));
 									videos.push(video);
 								}
 							}
+
+							// Imports of several files are undone in one step
+							history.push_all(added);
+							settings.save();
+						}
+
+						window.set_visible(true);
+					},
+					"z" if modifiers.control_key() => history.undo(&mut videos, &mut sources),
+					"y" if modifiers.control_key() => history.redo(&mut videos, &mut sources),
+					"s" | "S" if modifiers.control_key() => {
+						// Ctrl+Shift+S always asks where to save
+						let file = project_path.clone().filter(|_| key.as_str() == "s").or_else(|| {
+							window.set_visible(false);
+
+							let file = FileDialog::new()
+								.set_file_name(format!("project.{}", project::EXTENSION))
+								.set_directory(current_dir().unwrap())
+								.add_filter("Dusk project", &[project::EXTENSION])
+								.set_title("Save project")
+								.save_file();
+
+							window.set_visible(true);
+
+							file
+						});
+
+						if let Some(file) = file {
+							match Project::new(&videos, (size.width, size.height)).save(&file) {
+								Ok(()) => {
+									settings.remember_project(&file);
+									settings.save();
+									history.mark_saved();
+									project_path = Some(file);
+								},
+								Err(error) => {
+									MessageDialog::new()
+										.set_level(rfd::MessageLevel::Error)
+										.set_title("Saving failed")
+										.set_description(format!("{} couldn't be saved: {error}", file.display()))
+										.show();
+								}
+							}
+						}
+					},
+					"r" if modifiers.control_key() => recent_menu = true,
+					"u" if modifiers.control_key() => url_input = Some(String::new()),
+					"o" if modifiers.control_key() => {
+						window.set_visible(false);
+
+						let file = FileDialog::new()
+							.add_filter("Dusk project", &[project::EXTENSION])
+							.set_directory(current_dir().unwrap())
+							.set_title("Open project")
+							.pick_file();
+
+						if let Some(file) = file {
+							if let Some((width, height)) = open_project(&file, &mut videos, &mut sources) {
+								if let Some(audio) = &audio {
+									playback.stop(audio);
+								}
+
+								history = History::new(settings.history_limit);
+								settings.remember_project(&file);
+								settings.save();
+								project_path = Some(file);
+
+								// The surface is resized once the `Resized` event arrives
+								drop(window.request_inner_size(PhysicalSize::new(width, height)));
+							}
 						}
 
 						window.set_visible(true);
 					},
+					"m" => if let Some(video) = selected_video(&mut videos, &selection) {
+						// Conform the canvas and export frame rate to the selected clip
+						history.edit(video, |video| {
+							video.x = 0;
+							video.y = 0;
+							video.reset_size();
+						});
+
+						export_settings.fps = video.source.fps;
+
+						// The surface is resized once the `Resized` event arrives
+						let (width, height) = video.native_size();
+						drop(window.request_inner_size(PhysicalSize::new(width.get(), height.get())));
+					},
+					"o" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.end_behavior = video.end_behavior.next());
+					},
+					"-" | "=" => if let Some(video) = selected_video(&mut videos, &selection) {
+						let step = if key.as_str() == "=" { 0.1 } else { -0.1 };
+						history.edit(video, |video| video.opacity = (video.opacity + step).clamp(0.0, 1.0));
+					},
+					"c" if modifiers.control_key() => clipboard = videos.iter()
+						.filter(|video| selection.contains(&video.id))
+						.map(Video::state)
+						.collect(),
+					"v" if modifiers.control_key() => {
+						let first = videos.len();
+						let pasted = paste(&mut videos, &clipboard, playhead, &mut sources);
+
+						// The copies are selected instead, so that they can be moved into place
+						if !pasted.is_empty() {
+							selection = videos[first..].iter().map(|video| video.id).collect();
+						}
+
+						// Pasting several clips is undone in one step
+						history.push_all(pasted);
+					},
+					"c" => if let Some(video) = selected_video(&mut videos, &selection) {
+						// Crop away whatever lies outside the window
+						history.edit(video, |video| video.crop_to(IntRect::from_xywh(0, 0, size.width, size.height).unwrap()));
+					},
+					"C" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::uncrop);
+					},
+					"x" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::rotate);
+					},
+					"h" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::flip_horizontally);
+					},
+					"v" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::flip_vertically);
+					},
+					"{" | "}" => if let Some(video) = selected_video(&mut videos, &selection) {
+						let rate = if key.as_str() == "}" { video.rate * 2.0 } else { video.rate * 0.5 };
+						history.edit(video, |video| video.rate = rate.clamp(MIN_SPEED, MAX_SPEED));
+
+						// The clip's audio has to be restarted at it's new rate
+						if let (Some(audio), true) = (&audio, playing) {
+							playback.start(audio, &videos, playhead, speed);
+						}
+					},
+					"n" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.fade_in = if video.fade_in > 0.0 { 0.0 } else { FADE_LENGTH });
+					},
+					"N" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.fade_out = if video.fade_out > 0.0 { 0.0 } else { FADE_LENGTH });
+					},
+					"S" if !modifiers.control_key() => if let Some(i) = videos.iter().rposition(|video| selection.contains(&video.id)) {
+						if let Some(command) = split(&mut videos, i, playhead, &mut sources) {
+							history.push(command);
+						}
+					},
+					"_" | "+" => if let Some(video) = selected_video(&mut videos, &selection) {
+						let step = if key.as_str() == "+" { 0.1 } else { -0.1 };
+						history.edit(video, |video| video.volume = (video.volume + step).clamp(0.0, MAX_VOLUME));
+					},
+					"V" => if let Some(video) = selected_video(&mut videos, &selection) {
+						if video.source.video_streams > 1 {
+							history.edit(video, Video::next_stream);
+						}
+					},
+					"D" => {
+						settings.placement = settings.placement.next();
+						settings.save();
+					},
+					"F" => {
+						settings.scale_quality = settings.scale_quality.next();
+						settings.save();
+					},
+					"O" => {
+						settings.pip_corner = settings.pip_corner.next();
+						settings.save();
+					},
+					"B" => {
+						// The second of the two selected clips in drawing order is the one on top, which becomes the inset
+						let mut selected: Vec<_> = videos.iter_mut().filter(|video| selection.contains(&video.id)).collect();
+						selected.sort_by_key(|video| video.track);
+
+						if let [base, inset] = &mut selected[..] {
+							let track = inset.track.max(base.track.saturating_add(1));
+
+							history.edit(inset, |video| {
+								video.inset((size.width, size.height), settings.pip_corner, settings.pip_scale);
+								video.track = track;
+							});
+						}
+					},
+					"M" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.muted = !video.muted);
+					},
+					"s" if !modifiers.control_key() => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.solo = !video.solo);
+					},
+					"K" if modifiers.control_key() => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::clear_keyframes);
+					},
+					"K" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.add_keyframe(playhead));
+					},
+					"1" | "2" | "3" | "4" | "5" | "6" | "0" => if let Some(video) = selected_video(&mut videos, &selection) {
+						// Pairs of keys lower and raise brightness, contrast and saturation, while 0 resets all of them
+						let mut grade = video.grade();
+
+						match key.as_str() {
+							"1" => grade.brightness -= 0.05,
+							"2" => grade.brightness += 0.05,
+							"3" => grade.contrast -= 0.1,
+							"4" => grade.contrast += 0.1,
+							"5" => grade.saturation -= 0.1,
+							"6" => grade.saturation += 0.1,
+							_ => grade = Grade::default()
+						}
+
+						history.edit(video, |video| video.set_grade(grade));
+					},
+					"(" | ")" => if let Some(video) = selected_video(&mut videos, &selection).filter(|video| video.source.is_image) {
+						let step = if key.as_str() == ")" { 1.0 } else { -1.0 };
+						history.edit(video, |video| video.still_length = (video.still_length + step).max(1.0));
+					},
+					"p" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| {
+							video.scale_mode = ScaleMode::next(video.scale_mode);
+							video.fit((size.width, size.height));
+						});
+					},
+					"u" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.track = video.track.saturating_add(1));
+					},
+					"d" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, |video| video.track = video.track.saturating_sub(1));
+					},
+					"b" => if let Some(video) = selected_video(&mut videos, &selection) {
+						history.edit(video, Video::next_blend_mode);
+					},
+					"t" => export_settings.timecode = match export_settings.timecode {
+						Some(_) => None,
+						None => Some(TimecodeOverlay::default())
+					},
+					"T" => if let Some(timecode) = &mut export_settings.timecode {
+						timecode.corner = timecode.corner.next();
+					},
+					"r" => {
+						let i = OUTPUT_FPS_PRESETS.iter().position(|&fps| fps == export_settings.output_fps).map_or(0, |i| i + 1);
+						export_settings.output_fps = OUTPUT_FPS_PRESETS[i % OUTPUT_FPS_PRESETS.len()];
+					},
+					"R" => export_settings.fps_conversion = export_settings.fps_conversion.next(),
+					"P" => {
+						let current = export_settings.size.map(|(width, height)| (width, height, export_settings.fps));
+						let i = SIZE_PRESETS.iter().position(|&preset| preset == current).map_or(0, |i| i + 1);
+
+						if let Some((width, height, fps)) = SIZE_PRESETS[i % SIZE_PRESETS.len()] {
+							export_settings.size = Some((width, height));
+							export_settings.fps = fps;
+						} else {
+							export_settings.size = None;
+							export_settings.fps = ExportSettings::default().fps;
+						}
+					},
+					"I" => export_settings.region_only = !export_settings.region_only,
+					"E" => export_settings.codec = VideoCodec::next(export_settings.codec),
+					"G" => export_settings.audio_codec = AudioCodec::next(export_settings.audio_codec),
+					"j" => {
+						let i = CRF_PRESETS.iter().position(|&crf| crf == export_settings.crf).map_or(0, |i| i + 1);
+						export_settings.crf = CRF_PRESETS[i % CRF_PRESETS.len()];
+					},
+					"J" => {
+						let i = BITRATE_PRESETS.iter().position(|&bitrate| bitrate == export_settings.bitrate).map_or(0, |i| i + 1);
+						export_settings.bitrate = BITRATE_PRESETS[i % BITRATE_PRESETS.len()];
+					},
+					"[" => speed = speed.signum() * (speed.abs() * 0.5).max(MIN_SPEED),
+					"]" => speed = speed.signum() * (speed.abs() * 2.0).min(MAX_SPEED),
+					"\\" => speed = -speed,
+					"." | "," if !playing => if let Some(fps) = active_fps(&videos, playhead) {
+						// Step exactly one frame of the topmost clip under the playhead
+						let frame = (playhead * fps).round() + if key.as_str() == "." { 1.0 } else { -1.0 };
+						playhead = (frame / fps).max(0.0);
+
+						if let Some(audio) = &audio {
+							audio_scrub.scrub(audio, &videos, playhead);
+						}
+					},
+					"f" => snap_to_frames = !snap_to_frames,
+					"g" => seek_input = Some(String::new()),
+					"q" if !playing => loop_region.start = Some(playhead),
+					"w" if !playing => loop_region.end = Some(playhead),
+					"H" => {
+						// Applies to videos added from now on
+						let i = ffmpeg::HWACCELS.iter().position(|&hwaccel| hwaccel == ffmpeg::hwaccel()).map_or(0, |i| (i + 1) % ffmpeg::HWACCELS.len());
+						ffmpeg::set_hwaccel(ffmpeg::HWACCELS[i]);
+
+						settings.hwaccel = String::from(ffmpeg::HWACCELS[i]);
+						settings.save();
+					},
+					"k" => background_preset = background_preset.map_or(Some(0), |i| Some(i + 1).filter(|&i| i < BACKGROUND_PRESETS.len())),
+					"Q" => decode_quality = decode_quality.next(),
+					"l" => loop_region.mode = loop_region.mode.next(),
+					"L" => loop_project = !loop_project,
+					"a" => audio_scrub.enabled = !audio_scrub.enabled,
+					"A" => audio_scrub.preserve_pitch = !audio_scrub.preserve_pitch,
 					"e" => {
 						window.set_visible(false);
 
@@ -435,12 +2094,49 @@ fn main() {
 						let res = FileDialog::new()
 							.set_file_name("dusk-export")
 							.set_directory(path)
-							.add_filter("Video", VIDEO_EXTENSIONS)
+							.add_filter("Video", &formats.output)
 							.set_title("Export")
 							.save_file();
 
-						if let Some(file) = res {
-							println!("{file:?}");
+						// Motion interpolation can take many times longer than the rest of the export
+						let confirmed = export_settings.fps_conversion != FpsConversion::Interpolate || export_settings.output_fps.is_none() || MessageDialog::new()
+							.set_level(rfd::MessageLevel::Warning)
+							.set_title("Slow export")
+							.set_description("Interpolating frames to the output frame rate is very slow. Export anyway?")
+							.set_buttons(MessageButtons::YesNo)
+							.show() == MessageDialogResult::Yes;
+
+						// Dialogs let any extension be typed, but `FFmpeg` can only write the ones it has a muxer for
+						let res = res.filter(|file| formats.can_export(file) || {
+							MessageDialog::new()
+								.set_level(rfd::MessageLevel::Warning)
+								.set_title("Unsupported format")
+								.set_description(format!("FFmpeg can't export to {}. Supported formats are: {}", file.display(), formats.output.join(", ")))
+								.show();
+
+							false
+						});
+
+						if let Some(file) = res.filter(|_| confirmed) {
+							// The whole project is exported if no region is marked
+							let range = loop_region.range()
+								.filter(|_| export_settings.region_only)
+								.unwrap_or_else(|| project_range(&videos));
+
+							let canvas = canvas_color(background, background_preset);
+
+							match Export::start(&file, &mut videos, (size.width, size.height), range, canvas, &export_settings) {
+								Ok(export) => {
+									playing = false;
+
+									if let Some(audio) = &audio {
+										playback.stop(audio);
+									}
+
+									exporting = Some(export);
+								},
+								Err(error) => show_export_error(&file, &error)
+							}
 						}
 
 						window.set_visible(true);
@@ -456,21 +2152,53 @@ fn main() {
 				).unwrap();
 
 				size = new_size;
+
+				for video in &mut videos {
+					video.fit((size.width, size.height));
+				}
+
+				// Only the windowed geometry is remembered, so that leaving fullscreen mode on exit doesn't reopen a screen sized window
+				if window.fullscreen().is_none() {
+					settings.window_size = Some(new_size);
+					settings.save();
+				}
 			},
-			WindowEvent::DroppedFile(path) => if let Some(video) = Video::new(path, playhead) {
-				videos.push(video);
-				// set video start to current playhead
+			WindowEvent::Moved(position) if window.fullscreen().is_none() => {
+				settings.window_position = Some(position);
+				settings.save();
 			},
-			WindowEvent::ThemeChanged(theme) => background = match theme {
-				Theme::Dark => Color::from_rgba8(25, 25, 35, 255),
-				Theme::Light => Color::from_rgba8(225, 225, 235, 255)
+			// Files are dropped one event at a time, and each is added like an imported one
+			WindowEvent::DroppedFile(path) if !formats.can_import(&path) => {
+				MessageDialog::new()
+					.set_level(rfd::MessageLevel::Warning)
+					.set_title("Unsupported format")
+					.set_description(format!("{} can't be added. Supported formats are: {}", path.display(), formats.video.iter().chain(&formats.image).copied().collect::<Vec<_>>().join(", ")))
+					.show();
 			},
+			WindowEvent::DroppedFile(path) => if let Some(video) = add_video(&path, &videos, &selection, &settings, playhead, &mut sources) {
+				history.push(EditCommand::add(&video));
+				videos.push(video);
+				settings.remember_video(&path);
+				settings.save();
+			},
+			WindowEvent::ThemeChanged(theme) => background = theme_background(theme),
 			WindowEvent::CloseRequested => elwt.exit(),
 			_ => ()
 		},
-		Event::LoopExiting => for video in &mut videos {
-			drop(video.ffmpeg.quit());
+		Event::LoopExiting => {
+			for video in &mut videos {
+				drop(video.ffmpeg.quit());
+			}
+
+			if let Some(audio) = &audio {
+				playback.stop(audio);
+			}
+
+			settings.gui_enabled = gui_enabled;
+			settings.save();
 		},
 		_ => ()
 	}}).unwrap();
+
+	ExitCode::SUCCESS
 }
@@ -0,0 +1,56 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{source::Sources, video::{Video, VideoError, VideoState}};
+
+/// File extension of saved projects
+pub const EXTENSION: &str = "dusk";
+
+/// Everything needed to restore an editing session, saved as RON
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+	/// Size of the canvas, which is the inner size of the window
+	pub size: (u32, u32),
+	pub videos: Vec<VideoState>
+}
+
+impl Project {
+	/// Captures the current state of the project
+	pub fn new(videos: &[Video], size: (u32, u32)) -> Self {
+		Self {
+			size,
+			videos: videos.iter().map(Video::state).collect()
+		}
+	}
+
+	/// Writes the [`Project`] to a file
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		let text = ron::ser::to_string_pretty(self, PrettyConfig::default()).map_err(io::Error::other)?;
+
+		fs::write(path, text)
+	}
+
+	/// Reads a [`Project`] from a file
+	pub fn load(path: &Path) -> io::Result<Self> {
+		let text = fs::read_to_string(path)?;
+
+		ron::from_str(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+	}
+
+	/// Recreates the [`Project`]'s [`Video`]s, along with the paths of those which couldn't be loaded and why
+	pub fn videos(&self, sources: &mut Sources) -> (Vec<Video>, Vec<(PathBuf, VideoError)>) {
+		let mut videos = Vec::with_capacity(self.videos.len());
+		let mut failed = Vec::new();
+
+		for state in &self.videos {
+			match Video::from_state(state, sources) {
+				Ok(video) => videos.push(video),
+				Err(error) => failed.push((state.path.clone(), error))
+			}
+		}
+
+		(videos, failed)
+	}
+}
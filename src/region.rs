@@ -0,0 +1,68 @@
+use std::ops::RangeInclusive;
+
+/// Defines what happens when playback reaches the end of a [`LoopRegion`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+	Off,
+	Loop,
+	/// Reverses playback at either end of the region, bouncing back and forth between them
+	PingPong
+}
+
+impl LoopMode {
+	/// Returns the next [`LoopMode`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::Off => Self::Loop,
+			Self::Loop => Self::PingPong,
+			Self::PingPong => Self::Off
+		}
+	}
+}
+
+/// In and out points marking a region of the project, which playback can loop over
+pub struct LoopRegion {
+	pub start: Option<f32>,
+	pub end: Option<f32>,
+	pub mode: LoopMode
+}
+
+impl Default for LoopRegion {
+	fn default() -> Self {
+		Self {
+			start: None,
+			end: None,
+			mode: LoopMode::Off
+		}
+	}
+}
+
+impl LoopRegion {
+	/// Returns the marked region, if both points are set in the right order
+	pub fn range(&self) -> Option<RangeInclusive<f32>> {
+		match (self.start, self.end) {
+			(Some(start), Some(end)) if start < end => Some(start..=end),
+			_ => None
+		}
+	}
+
+	/// Returns where the playhead should jump to and the speed to continue playing at if it has left the region while looping
+	///
+	/// Playing forwards wraps from the end to the start, and playing in reverse from the start to the end, unless ping-ponging, which turns around at the edge instead
+	pub fn wrap(&self, playhead: f32, speed: f32) -> Option<(f32, f32)> {
+		if self.mode == LoopMode::Off {
+			return None;
+		}
+
+		let range = self.range()?;
+		let bounce = self.mode == LoopMode::PingPong;
+
+		if speed >= 0.0 && playhead > *range.end() {
+			Some(if bounce { (*range.end(), -speed) } else { (*range.start(), speed) })
+		} else if speed < 0.0 && playhead < *range.start() {
+			Some(if bounce { (*range.start(), -speed) } else { (*range.end(), speed) })
+		} else {
+			None
+		}
+	}
+}
@@ -1,71 +1,276 @@
 use rayon_macro::parallel;
-use tiny_skia::{BlendMode, Color, FilterQuality, NonZeroRect, PixmapMut, PixmapPaint, Transform};
+use tiny_skia::{Color, FilterQuality, NonZeroRect, PixmapMut, PixmapPaint, Transform};
 
 use crate::video::Video;
 
+/// How frames are filtered when they're drawn at a different size than they were decoded at, trading quality for speed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScaleQuality {
+	Nearest,
+	Bilinear,
+	Bicubic
+}
+
+impl ScaleQuality {
+	/// Returns the next [`ScaleQuality`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::Nearest => Self::Bilinear,
+			Self::Bilinear => Self::Bicubic,
+			Self::Bicubic => Self::Nearest
+		}
+	}
+
+	/// Returns the name the [`ScaleQuality`] is saved and shown as
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Nearest => "nearest",
+			Self::Bilinear => "bilinear",
+			Self::Bicubic => "bicubic"
+		}
+	}
+
+	const fn filter(self) -> FilterQuality {
+		match self {
+			Self::Nearest => FilterQuality::Nearest,
+			Self::Bilinear => FilterQuality::Bilinear,
+			Self::Bicubic => FilterQuality::Bicubic
+		}
+	}
+}
+
+/// Area a [`Video`]'s current frame is drawn over and whether it hides what's beneath it there, which is all that occlusion depends on
+///
+/// Occlusion is worked out from [`Layer`]s rather than [`Video`]s so that it can be checked without decoding anything
+#[derive(Clone, Copy)]
+struct Layer {
+	left: i32,
+	top: i32,
+	right: i32,
+	bottom: i32,
+	/// Translucent or blended videos let the ones beneath them show through
+	opaque: bool
+}
+
+impl Layer {
+	/// Returns the [`Layer`] of a [`Video`], which it has none of without a frame
+	fn of(video: &Video) -> Option<Self> {
+		video.size().map(|(width, height)| Self {
+			left: video.x,
+			top: video.y,
+			right: video.x + width as i32,
+			bottom: video.y + height as i32,
+			opaque: video.is_opaque()
+		})
+	}
+
+	/// Returns whether the [`Layer`] lies completely within `other`, where sharing an edge still counts
+	const fn is_within(self, other: Self) -> bool {
+		other.left <= self.left &&
+		other.right >= self.right &&
+		other.top <= self.top &&
+		other.bottom >= self.bottom
+	}
+}
+
+/// Returns whether the `i`th [`Layer`] is completely hidden behind an opaque one drawn over it, which it can't be without a frame
+fn is_covered(layers: &[Option<Layer>], i: usize) -> bool {
+	layers[i].is_some_and(|layer| layers[(i + 1)..].iter().flatten().any(|&other| other.opaque && layer.is_within(other)))
+}
+
+/// Returns which [`Layer`]s don't have to be drawn, being hidden or having no frame, and whether the `width` by `height` canvas has to be cleared first, which it doesn't if an opaque [`Layer`] covers all of it
+fn occlusion(layers: &[Option<Layer>], width: i32, height: i32) -> (Vec<bool>, bool) {
+	let canvas = Layer {
+		left: 0,
+		top: 0,
+		right: width,
+		bottom: height,
+		opaque: false
+	};
+
+	let fill = !layers.iter().flatten().any(|layer| layer.opaque && canvas.is_within(*layer));
+	let occluded = (0..layers.len()).map(|i| layers[i].is_none() || is_covered(layers, i)).collect();
+
+	(occluded, fill)
+}
+
+/// Returns the [`Layer`]s of the [`Video`]s as they were last loaded, in the order they're drawn
+fn layers_of(videos: &[Video], order: &[usize]) -> Vec<Option<Layer>> {
+	order.iter().map(|&i| Layer::of(&videos[i])).collect()
+}
+
+/// Returns which [`Video`]s are completely hidden behind opaque ones drawn over them, by their index
+fn covered(videos: &[Video], order: &[usize]) -> Vec<bool> {
+	let layers = layers_of(videos, order);
+	let mut covered = vec![false; videos.len()];
+
+	for (position, &i) in order.iter().enumerate() {
+		covered[i] = is_covered(&layers, position);
+	}
+
+	covered
+}
+
+/// Returns the indices of the [`Video`]s in the order they're drawn, which is by track, keeping their order within each track, so that later ones are drawn on top
+pub fn draw_order(videos: &[Video]) -> Vec<usize> {
+	let mut order: Vec<_> = (0..videos.len()).collect();
+	order.sort_by_key(|&i| videos[i].track); // Stable, so clips on the same track stay in order
+
+	order
+}
+
 /// Clears the buffer and loads and draws all [`Video`]s to it
-pub fn render_frame(pixmap: &mut PixmapMut, videos: &mut [Video], playhead: f32, background: Color) {
-	parallel!(for video in &mut *videos {
-		video.load(playhead);
+///
+/// This is the only renderer, used for the preview as well as for exports, so both always show the same thing
+///
+/// The [`Video`]s are drawn in their [`draw_order`], while the slice itself is left in the order the project keeps it in
+///
+/// Everything is drawn through `view`, for rendering at a different resolution than the [`Video`]s are laid out at, with the background filling any bars left around them
+///
+/// Frames are scaled with the given [`ScaleQuality`], except for those of [`Video`]s being resized, which use the fastest one until they're decoded at their new size
+///
+/// At most `max_decoders` [`Video`]s keep their `FFmpeg` instances running, with the topmost ones on the playhead picked first, and the rest of them on the playhead keep showing their last frame
+pub fn render_frame(pixmap: &mut PixmapMut, videos: &mut [Video], playhead: f32, background: Color, view: Transform, quality: ScaleQuality, max_decoders: usize) {
+	let order = draw_order(videos);
+
+	// Clips hidden behind opaque clips above them aren't decoded, going by where everything was last frame
+	let covered = covered(videos, &order);
+
+	let mut priority: Vec<_> = order.iter().rev().copied().collect();
+	priority.sort_by_key(|&i| covered[i] || !videos[i].is_active(playhead)); // Stable, so the topmost stay first
+
+	let mut decoding = vec![false; videos.len()];
+
+	for (rank, i) in priority.into_iter().enumerate() {
+		if rank < max_decoders {
+			decoding[i] = true;
+		}
+
+		// Covered clips quit decoding too, so that they seek once uncovered instead of decoding every frame they missed
+		if rank >= max_decoders || covered[i] {
+			videos[i].suspend();
+		}
+	}
+
+	// Suspended videos which aren't on the playhead are still loaded to hide them, which doesn't need `FFmpeg`
+	parallel!(for (video, &decoding, &covered) in (&mut *videos, &decoding, &covered) {
+		if (decoding && !covered) || !video.is_active(playhead) {
+			video.load(playhead);
+		}
 	});
 
-	let mut fill = true;
-
-	#[allow(clippy::needless_collect)] // Required for `fill`
-	let occlusion: Vec<_> = videos.iter().enumerate().map(|(i, video)| {
-		video.frame.as_ref().map_or(true, |frame| {
-			let x = video.x;
-			let y = video.y;
-			let w = frame.width() as i32;
-			let h = frame.height() as i32;
-
-			if
-				x <= 0 &&
-				x + w >= pixmap.width() as i32 &&
-				y <= 0 &&
-				y + h >= pixmap.height() as i32
-			{
-				fill = false;
-			}
-
-			videos[(i + 1)..].iter().any(|other| {
-				other.frame.as_ref().map_or(false, |other_frame|
-					other.x <= x && // left
-					other.x + other_frame.width() as i32 >= x + w && // right
-					other.y <= y && // top
-					other.y + other_frame.height() as i32 >= y + h // bottom
-				)
-			})
-		})
-	}).collect();
+	// Clips uncovered by what was just loaded are loaded right away, so that they don't show an old frame
+	let still_covered = self::covered(videos, &order);
+	let uncovered: Vec<_> = (0..videos.len()).map(|i| covered[i] && decoding[i] && !still_covered[i]).collect();
+
+	parallel!(for (video, &uncovered) in (&mut *videos, &uncovered) {
+		if uncovered {
+			video.load(playhead);
+		}
+	});
+
+	// Size of the pixmap in the coordinates the videos are laid out in, without the bars around them
+	let width = (view.tx.mul_add(-2.0, pixmap.width() as f32) / view.sx).ceil() as i32;
+	let height = (view.ty.mul_add(-2.0, pixmap.height() as f32) / view.sy).ceil() as i32;
+
+	let (occlusion, fill) = occlusion(&layers_of(videos, &order), width, height);
 
-	if fill {
+	if fill || view.tx > 0.0 || view.ty > 0.0 {
 		pixmap.fill(background);
 	}
 
-	for (video, occluded) in videos.iter().zip(occlusion.into_iter()) { if !occluded {
-		if let Some(frame) = &video.frame {
+	for (video, occluded) in order.iter().map(|&i| &videos[i]).zip(occlusion) { if !occluded {
+		if let Some(frame) = video.frame.as_deref() {
 			pixmap.draw_pixmap(
 				video.x,
 				video.y,
 				frame.as_ref(),
 				&PixmapPaint {
-					blend_mode: BlendMode::Source,
-					quality: FilterQuality::Bilinear, // Severe performance impact while resizing videos
-					..Default::default()
+					opacity: video.alpha(),
+					blend_mode: video.effective_blend_mode(),
+					quality: if video.scale.is_some() { FilterQuality::Nearest } else { quality.filter() }
 				},
-				if let Some((sx, sy)) = video.scale {
+				if let Some((scale_x, scale_y)) = video.draw_scale() {
 					NonZeroRect::from_xywh(
-						video.x as f32 * (1.0 - sx),
-						video.y as f32 * (1.0 - sy),
-						sx,
-						sy
+						video.x as f32 * (1.0 - scale_x),
+						video.y as f32 * (1.0 - scale_y),
+						scale_x,
+						scale_y
 					).map_or_else(Transform::identity, Transform::from_bbox)
 				} else {
 					Transform::identity()
-				},
+				}.post_concat(view),
 				None
 			);
 		}
 	}}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use tiny_skia::BlendMode;
+
+	use super::*;
+	use crate::{source::Sources, video::tests::test_clip};
+
+	const fn layer(left: i32, top: i32, right: i32, bottom: i32, opaque: bool) -> Layer {
+		Layer { left, top, right, bottom, opaque }
+	}
+
+	#[test]
+	fn partial_overlap_isnt_covered() {
+		let layers = [Some(layer(0, 0, 100, 100, true)), Some(layer(50, 50, 150, 150, true))];
+
+		assert!(!is_covered(&layers, 0));
+		assert_eq!(occlusion(&layers, 200, 200).0, [false, false]);
+	}
+
+	#[test]
+	fn shared_edges_are_covered() {
+		let layers = [Some(layer(0, 0, 100, 100, true)), Some(layer(20, 20, 100, 100, true)), Some(layer(0, 0, 100, 100, true))];
+
+		assert!(is_covered(&layers, 0));
+		assert!(is_covered(&layers, 1));
+		assert!(!is_covered(&layers, 2), "Nothing is drawn over the topmost layer");
+	}
+
+	#[test]
+	fn translucent_layers_dont_occlude() {
+		let layers = [Some(layer(0, 0, 100, 100, true)), Some(layer(0, 0, 100, 100, false))];
+
+		assert!(!is_covered(&layers, 0));
+		assert_eq!(occlusion(&layers, 100, 100).0, [false, false]);
+	}
+
+	#[test]
+	fn fullscreen_opaque_layers_skip_the_background() {
+		let layers = [Some(layer(0, 0, 1920, 1080, true)), None, Some(layer(0, 0, 1920, 1080, true))];
+		assert_eq!(occlusion(&layers, 1920, 1080), (vec![true, true, false], false));
+
+		let layers = [Some(layer(0, 0, 1920, 1080, false))];
+		assert!(occlusion(&layers, 1920, 1080).1, "The background shows through translucent layers");
+
+		let layers = [Some(layer(0, 0, 1920, 1079, true))];
+		assert!(occlusion(&layers, 1920, 1080).1, "The background shows around smaller layers");
+	}
+
+	#[test]
+	fn only_opaque_replacing_videos_occlude() {
+		let Some(path) = test_clip("occlusion", 1, 10) else { return };
+		let Ok(mut video) = Video::new(path.clone(), 0.0, &mut Sources::default()) else { panic!("Couldn't open {}", path.display()) };
+		video.load(0.0);
+
+		video.blend_mode = BlendMode::Source;
+		assert!(Layer::of(&video).is_some_and(|layer| layer.opaque));
+
+		video.blend_mode = BlendMode::SourceOver;
+		assert!(Layer::of(&video).is_some_and(|layer| !layer.opaque), "Blending over lets transparent sources show what's beneath");
+
+		video.blend_mode = BlendMode::Source;
+		video.opacity = 0.5;
+		assert!(Layer::of(&video).is_some_and(|layer| !layer.opaque), "Translucent videos show what's beneath");
+
+		drop(video.ffmpeg.quit());
+		drop(std::fs::remove_file(path));
+	}
+}
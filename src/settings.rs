@@ -0,0 +1,295 @@
+use std::{env::var_os, fmt::Write, fs, path::{Path, PathBuf}};
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::{edit, ffmpeg, render::ScaleQuality, source::is_url};
+
+/// Number of recently used videos, and separately projects, which are remembered
+pub const RECENT_LIMIT: usize = 5;
+
+/// Moves `path` to the front of a list of recently used files, forgetting the oldest ones beyond [`RECENT_LIMIT`]
+fn remember(recent: &mut Vec<PathBuf>, path: &Path) {
+	recent.retain(|recent| recent != path);
+	recent.insert(0, path.to_path_buf());
+	recent.truncate(RECENT_LIMIT);
+}
+
+/// Where videos are placed on the timeline when they're added
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+	Playhead,
+	ProjectStart,
+	/// Right after the last clip on the track they're added to, for laying out a sequence of clips
+	Append
+}
+
+impl Placement {
+	/// Returns the next [`Placement`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::Playhead => Self::ProjectStart,
+			Self::ProjectStart => Self::Append,
+			Self::Append => Self::Playhead
+		}
+	}
+
+	const fn name(self) -> &'static str {
+		match self {
+			Self::Playhead => "playhead",
+			Self::ProjectStart => "start",
+			Self::Append => "append"
+		}
+	}
+}
+
+/// Corner of the canvas which picture-in-picture insets are placed in
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	BottomRight
+}
+
+impl Corner {
+	/// Returns the next [`Corner`] clockwise, for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::TopLeft => Self::TopRight,
+			Self::TopRight => Self::BottomRight,
+			Self::BottomRight => Self::BottomLeft,
+			Self::BottomLeft => Self::TopLeft
+		}
+	}
+
+	const fn name(self) -> &'static str {
+		match self {
+			Self::TopLeft => "top_left",
+			Self::TopRight => "top_right",
+			Self::BottomLeft => "bottom_left",
+			Self::BottomRight => "bottom_right"
+		}
+	}
+}
+
+/// Fraction of the canvas picture-in-picture insets fit within unless configured otherwise
+const DEFAULT_PIP_SCALE: f32 = 0.25;
+
+/// Number of videos decoded at once unless configured otherwise
+const DEFAULT_MAX_DECODERS: usize = 16;
+
+/// Seconds the playhead is moved by with the arrow keys
+pub struct ScrubConfig {
+	pub coarse: f32,
+	/// Used while Ctrl is held
+	pub fine: f32
+}
+
+impl Default for ScrubConfig {
+	fn default() -> Self {
+		Self {
+			coarse: 1.0,
+			fine: 0.1
+		}
+	}
+}
+
+/// Parses a step size, which has to be positive
+fn parse_step(value: &str) -> Option<f32> {
+	value.trim().parse().ok().filter(|step: &f32| step.is_finite() && *step > 0.0)
+}
+
+/// User settings which are remembered between runs, stored as `key=value` lines
+pub struct Settings {
+	pub window_position: Option<PhysicalPosition<i32>>,
+	pub window_size: Option<PhysicalSize<u32>>,
+	pub gui_enabled: bool,
+	/// `FFmpeg` executable picked by the user when none could be found
+	pub ffmpeg_path: Option<PathBuf>,
+	/// Maximum number of edits which can be undone
+	pub history_limit: usize,
+	/// Hardware acceleration backend used for decoding, one of [`ffmpeg::HWACCELS`]
+	pub hwaccel: String,
+	pub scrub: ScrubConfig,
+	/// Most times per second the preview is redrawn, which is the monitor's refresh rate if `None`
+	pub preview_fps_limit: Option<f32>,
+	/// Most `FFmpeg` instances decoding videos for the preview at once
+	pub max_decoders: usize,
+	/// Threads each `FFmpeg` instance decodes with, which are shared out between the clips on the playhead if `None`
+	pub decoder_threads: Option<usize>,
+	pub placement: Placement,
+	/// How the preview filters frames shown at a different size than they were decoded at
+	pub scale_quality: ScaleQuality,
+	pub pip_corner: Corner,
+	/// Fraction of the canvas' width and height picture-in-picture insets fit within
+	pub pip_scale: f32,
+	/// Videos and images most recently added, newest first
+	pub recent_videos: Vec<PathBuf>,
+	/// Projects most recently saved or opened, newest first
+	pub recent_projects: Vec<PathBuf>
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			window_position: None,
+			window_size: None,
+			gui_enabled: true,
+			ffmpeg_path: None,
+			history_limit: edit::DEFAULT_LIMIT,
+			hwaccel: String::from(ffmpeg::HWACCELS[0]),
+			scrub: ScrubConfig::default(),
+			preview_fps_limit: None,
+			max_decoders: DEFAULT_MAX_DECODERS,
+			decoder_threads: None,
+			placement: Placement::Playhead,
+			scale_quality: ScaleQuality::Bilinear,
+			pip_corner: Corner::BottomRight,
+			pip_scale: DEFAULT_PIP_SCALE,
+			recent_videos: Vec::new(),
+			recent_projects: Vec::new()
+		}
+	}
+}
+
+/// Parses a pair of comma separated numbers
+fn parse_pair<T: std::str::FromStr>(value: &str) -> Option<(T, T)> {
+	let (a, b) = value.split_once(',')?;
+
+	Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+impl Settings {
+	/// Returns the path of the settings file in the platform's configuration directory
+	fn path() -> Option<PathBuf> {
+		let dir = if cfg!(windows) {
+			var_os("APPDATA").map(PathBuf::from)
+		} else {
+			var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+		};
+
+		dir.map(|dir| dir.join("dusk").join("settings.txt"))
+	}
+
+	/// Loads the saved [`Settings`], using defaults for anything missing or invalid
+	pub fn load() -> Self {
+		let mut settings = Self::default();
+
+		let Some(text) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else { return settings };
+
+		for (key, value) in text.lines().filter_map(|line| line.split_once('=')) {
+			match key.trim() {
+				"window_position" => settings.window_position = parse_pair(value).map(|(x, y)| PhysicalPosition::new(x, y)),
+				"window_size" => settings.window_size = parse_pair(value).map(|(w, h)| PhysicalSize::new(w, h)),
+				"gui_enabled" => settings.gui_enabled = value.trim() != "false",
+				"ffmpeg_path" => settings.ffmpeg_path = Some(PathBuf::from(value.trim())),
+				"history_limit" => if let Ok(limit) = value.trim().parse() {
+					settings.history_limit = limit;
+				},
+				"hwaccel" => settings.hwaccel = String::from(value.trim()),
+				"scrub_coarse" => if let Some(step) = parse_step(value) {
+					settings.scrub.coarse = step;
+				},
+				"scrub_fine" => if let Some(step) = parse_step(value) {
+					settings.scrub.fine = step;
+				},
+				"preview_fps_limit" => settings.preview_fps_limit = parse_step(value),
+				"placement" => if let Some(placement) = [Placement::Playhead, Placement::ProjectStart, Placement::Append].into_iter().find(|placement| placement.name() == value.trim()) {
+					settings.placement = placement;
+				},
+				"scale_quality" => if let Some(quality) = [ScaleQuality::Nearest, ScaleQuality::Bilinear, ScaleQuality::Bicubic].into_iter().find(|quality| quality.name() == value.trim()) {
+					settings.scale_quality = quality;
+				},
+				"pip_corner" => if let Some(corner) = [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight].into_iter().find(|corner| corner.name() == value.trim()) {
+					settings.pip_corner = corner;
+				},
+				"pip_scale" => if let Some(scale) = parse_step(value).filter(|&scale| scale <= 1.0) {
+					settings.pip_scale = scale;
+				},
+				"decoder_threads" => settings.decoder_threads = value.trim().parse().ok().filter(|&threads| threads > 0),
+				"max_decoders" => if let Some(max) = value.trim().parse().ok().filter(|&max| max > 0) {
+					settings.max_decoders = max;
+				},
+				// Files which have since been moved or deleted are forgotten, while streams can't be checked without opening them
+				"recent_video" if (is_url(Path::new(value.trim())) || Path::new(value.trim()).exists()) && settings.recent_videos.len() < RECENT_LIMIT => {
+					settings.recent_videos.push(PathBuf::from(value.trim()));
+				},
+				"recent_project" if Path::new(value.trim()).exists() && settings.recent_projects.len() < RECENT_LIMIT => {
+					settings.recent_projects.push(PathBuf::from(value.trim()));
+				},
+				_ => ()
+			}
+		}
+
+		settings
+	}
+
+	/// Remembers a video or image as the most recently added one
+	pub fn remember_video(&mut self, path: &Path) {
+		remember(&mut self.recent_videos, path);
+	}
+
+	/// Remembers a project as the most recently saved or opened one
+	pub fn remember_project(&mut self, path: &Path) {
+		remember(&mut self.recent_projects, path);
+	}
+
+	/// Returns every remembered file, projects first, in the order they're listed in the recent files menu
+	pub fn recent(&self) -> impl Iterator<Item = &PathBuf> {
+		self.recent_projects.iter().chain(&self.recent_videos)
+	}
+
+	/// Writes the [`Settings`] to disk, ignoring failures since they're only a convenience
+	pub fn save(&self) {
+		let Some(path) = Self::path() else { return };
+
+		let mut text = String::new();
+
+		if let Some(position) = self.window_position {
+			writeln!(text, "window_position={},{}", position.x, position.y).unwrap();
+		}
+
+		if let Some(size) = self.window_size {
+			writeln!(text, "window_size={},{}", size.width, size.height).unwrap();
+		}
+
+		writeln!(text, "gui_enabled={}", self.gui_enabled).unwrap();
+
+		if let Some(path) = &self.ffmpeg_path {
+			writeln!(text, "ffmpeg_path={}", path.display()).unwrap();
+		}
+
+		writeln!(text, "history_limit={}", self.history_limit).unwrap();
+		writeln!(text, "hwaccel={}", self.hwaccel).unwrap();
+		writeln!(text, "scrub_coarse={}", self.scrub.coarse).unwrap();
+		writeln!(text, "scrub_fine={}", self.scrub.fine).unwrap();
+
+		if let Some(limit) = self.preview_fps_limit {
+			writeln!(text, "preview_fps_limit={limit}").unwrap();
+		}
+
+		writeln!(text, "max_decoders={}", self.max_decoders).unwrap();
+
+		if let Some(threads) = self.decoder_threads {
+			writeln!(text, "decoder_threads={threads}").unwrap();
+		}
+		writeln!(text, "placement={}", self.placement.name()).unwrap();
+		writeln!(text, "scale_quality={}", self.scale_quality.name()).unwrap();
+		writeln!(text, "pip_corner={}", self.pip_corner.name()).unwrap();
+		writeln!(text, "pip_scale={}", self.pip_scale).unwrap();
+
+		for path in &self.recent_videos {
+			writeln!(text, "recent_video={}", path.display()).unwrap();
+		}
+
+		for path in &self.recent_projects {
+			writeln!(text, "recent_project={}", path.display()).unwrap();
+		}
+
+		if let Some(dir) = path.parent() {
+			drop(fs::create_dir_all(dir));
+		}
+
+		drop(fs::write(path, text));
+	}
+}
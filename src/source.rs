@@ -0,0 +1,175 @@
+use std::{collections::HashMap, fs, num::NonZeroU32, path::{Path, PathBuf}, sync::Arc, time::SystemTime};
+
+use ffmpeg_sidecar::{event::AVStream, metadata::FfmpegMetadata};
+
+use crate::video::VideoError;
+
+/// Pixel formats which frames can be decoded in and turned into RGBA without `FFmpeg` converting them, the first being the one converted to for other sources
+const PIX_FMTS: [&str; 4] = ["rgba", "bgra", "rgb24", "yuv420p"];
+
+/// Frame rate given to images which `FFmpeg` reports none for, so that the playhead can still be stepped over them
+const STILL_FPS: f32 = 30.0;
+
+/// Returns the first video stream `FFmpeg` outputs, skipping any audio, subtitle or data streams which come before it in the file
+pub fn video_stream(metadata: &FfmpegMetadata) -> Option<&AVStream> {
+	metadata.output_streams.iter().find(|stream| stream.stream_type == "Video")
+}
+
+/// Returns whether a path is a URL such as `https://` or `rtsp://` for `FFmpeg` to read over the network, rather than a local file
+pub fn is_url(path: &Path) -> bool {
+	path.to_str().is_some_and(|path| path.split_once("://").is_some_and(|(scheme, _)| !scheme.is_empty() && scheme.chars().all(|char| char.is_ascii_alphanumeric() || matches!(char, '+' | '-' | '.'))))
+}
+
+/// Returns whether frames of a resolution can be decoded in a pixel format, which isn't the case for odd resolutions with subsampled chroma
+fn fits(pix_fmt: &str, (width, height): (u32, u32)) -> bool {
+	pix_fmt != "yuv420p" || (width % 2 == 0 && height % 2 == 0)
+}
+
+/// Metadata about a media file, shared by every [`Video`](crate::video::Video) referencing it
+pub struct Source {
+	pub path: PathBuf,
+	pub width: NonZeroU32,
+	pub height: NonZeroU32,
+	pub fps: f32,
+	/// Pixel format frames are decoded to, which is the native format when possible
+	pub pix_fmt: &'static str,
+	/// Length in seconds, if `FFmpeg` could detect it
+	pub length: Option<f32>,
+	pub has_audio: bool,
+	/// Number of video streams in the file, such as the angles of a multi-angle recording, which is at least `1`
+	pub video_streams: usize,
+	/// Whether the file is a still image, which is shown for a length set by the user rather than it's own
+	pub is_image: bool,
+	/// Whether the source is a network stream of unknown length, which can only be read as it arrives and not seeked in
+	pub is_live: bool
+}
+
+impl Source {
+	/// Reads the [`Source`] from the metadata of an `FFmpeg` instance decoding it
+	fn from_metadata(path: PathBuf, metadata: &FfmpegMetadata) -> Result<Self, VideoError> {
+		let stream = video_stream(metadata).ok_or(VideoError::NotVideo)?;
+
+		// Inputs are logged as "Input #0, png_pipe, from 'image.png':", where single images are read by `image2` or one of the `_pipe` demuxers
+		let is_image = metadata.inputs.first()
+			.and_then(|input| input.raw_log_message.split(", ").nth(1))
+			.is_some_and(|format| format == "image2" || format.ends_with("_pipe"));
+
+		if stream.fps == 0.0 && !is_image {
+			return Err(VideoError::NotVideo);
+		}
+
+		let length = metadata.inputs.first().and_then(|input| input.duration).map(|length| length as f32).filter(|_| !is_image);
+
+		Ok(Self {
+			is_live: length.is_none() && is_url(&path),
+			path,
+			width: NonZeroU32::new(stream.width).ok_or(VideoError::ZeroSize)?,
+			height: NonZeroU32::new(stream.height).ok_or(VideoError::ZeroSize)?,
+			fps: if stream.fps > 0.0 { stream.fps } else { STILL_FPS },
+			pix_fmt: PIX_FMTS.iter().find(|&&pix_fmt| pix_fmt == stream.pix_fmt && fits(pix_fmt, (stream.width, stream.height))).unwrap_or(&PIX_FMTS[0]),
+			length,
+			has_audio: metadata.input_streams.iter().any(|stream| stream.stream_type == "Audio"),
+			video_streams: metadata.input_streams.iter().filter(|stream| stream.stream_type == "Video").count().max(1),
+			is_image
+		})
+	}
+
+	/// Returns the pixel format frames are decoded to at a resolution, which is RGBA if it doesn't fit the source's own format
+	pub fn pix_fmt_at(&self, size: (u32, u32)) -> &'static str {
+		if fits(self.pix_fmt, size) { self.pix_fmt } else { PIX_FMTS[0] }
+	}
+}
+
+/// Returns when a file was last modified, if the platform keeps track of it
+fn modified(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Cache of [`Source`]s keyed by canonicalized path, so that several clips of the same file share their metadata
+///
+/// Files found not to contain a usable video stream are remembered as well, so that adding them again fails without starting `FFmpeg`, unless they've been changed since
+#[derive(Default)]
+pub struct Sources {
+	loaded: HashMap<PathBuf, Arc<Source>>,
+	/// When each rejected file was last modified, and whether it was rejected for having a size of zero rather than having no video stream
+	rejected: HashMap<PathBuf, (Option<SystemTime>, bool)>
+}
+
+impl Sources {
+	/// Canonicalizes a path so that different ways of referring to one file share a cache entry
+	fn key(path: &Path) -> PathBuf {
+		path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+	}
+
+	/// Returns the cached [`Source`] for a path, if it has been loaded before
+	pub fn get(&self, path: &Path) -> Option<Arc<Source>> {
+		self.loaded.get(&Self::key(path)).cloned()
+	}
+
+	/// Returns why a path was rejected before, if it hasn't been modified since
+	pub fn rejected(&self, path: &Path) -> Option<VideoError> {
+		let &(time, zero_size) = self.rejected.get(&Self::key(path))?;
+
+		if time != modified(path) {
+			return None;
+		}
+
+		Some(if zero_size { VideoError::ZeroSize } else { VideoError::NotVideo })
+	}
+
+	/// Creates a [`Source`] from `FFmpeg` metadata and caches it
+	pub fn insert(&mut self, path: PathBuf, metadata: &FfmpegMetadata) -> Result<Arc<Source>, VideoError> {
+		let key = Self::key(&path);
+
+		let source = match Source::from_metadata(path, metadata) {
+			Ok(source) => Arc::new(source),
+			Err(error) => {
+				// Errors from `FFmpeg` itself may only be with the hardware acceleration backend, so only missing or empty video streams are remembered
+				if let VideoError::NotVideo | VideoError::ZeroSize = error {
+					self.rejected.insert(key.clone(), (modified(&key), matches!(error, VideoError::ZeroSize)));
+				}
+
+				return Err(error);
+			}
+		};
+
+		self.rejected.remove(&key);
+		self.loaded.insert(key, source.clone());
+
+		Ok(source)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn stream(stream_type: &str) -> AVStream {
+		AVStream {
+			stream_type: stream_type.to_owned(),
+			format: String::new(),
+			pix_fmt: String::new(),
+			width: 0,
+			height: 0,
+			fps: 0.0,
+			parent_index: 0,
+			raw_log_message: String::new()
+		}
+	}
+
+	#[test]
+	fn video_stream_skips_audio_before_it() {
+		let mut metadata = FfmpegMetadata::default();
+		metadata.output_streams = vec![stream("Audio"), stream("Video"), stream("Subtitle")];
+
+		assert_eq!(video_stream(&metadata).map(|stream| stream.stream_type.as_str()), Some("Video"));
+	}
+
+	#[test]
+	fn video_stream_is_none_without_video() {
+		let mut metadata = FfmpegMetadata::default();
+		metadata.output_streams = vec![stream("Audio")];
+
+		assert!(video_stream(&metadata).is_none());
+	}
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::{Arc, OnceLock}, thread};
+
+use tiny_skia::{IntSize, Pixmap};
+
+use crate::{ffmpeg, source::Source};
+
+/// Height in pixels at which thumbnails are decoded, they're scaled to fit the timeline when drawn
+pub const HEIGHT: u32 = 48;
+
+/// Decodes a single small frame of a file at a timestamp
+fn decode(path: &Path, timestamp: f32, width: u32) -> Option<Pixmap> {
+	let mut ffmpeg = ffmpeg::command()
+		.hide_banner()
+		.create_no_window()
+		.no_audio()
+		.args(["-sn", "-dn"])
+		.seek(timestamp.to_string())
+		.input(path.to_str()?)
+		.frames(1)
+		.format("rawvideo")
+		.pix_fmt("rgba")
+		.size(width, HEIGHT)
+		.pipe_stdout()
+		.spawn().ok()?;
+
+	let frame = ffmpeg.iter().ok()?.filter_frames().next();
+
+	drop(ffmpeg.wait());
+
+	frame.and_then(|frame| Pixmap::from_vec(frame.data, IntSize::from_wh(frame.width, frame.height)?))
+}
+
+/// Filmstrips of thumbnails spread evenly across [`Source`]s, keyed by path and thumbnail count
+///
+/// Each filmstrip is generated on it's own thread the first time it's requested, with counts rounded up to powers of two so that zooming only ever starts a few of them per [`Source`]
+#[derive(Default)]
+pub struct Thumbnails(HashMap<(PathBuf, usize), Arc<OnceLock<Vec<Pixmap>>>>);
+
+impl Thumbnails {
+	/// Returns at least `count` thumbnails spread evenly across a [`Source`], or the most of them that have already been generated while they're still being generated
+	///
+	/// Sources of unknown length have no thumbnails
+	pub fn get(&mut self, source: &Source, count: usize) -> Option<&[Pixmap]> {
+		let length = source.length?;
+		let count = count.next_power_of_two();
+
+		let strip = self.0.entry((source.path.clone(), count)).or_insert_with(|| {
+			let strip = Arc::<OnceLock<_>>::default();
+
+			let path = source.path.clone();
+			let width = (HEIGHT as f32 * source.width.get() as f32 / source.height.get() as f32).round().max(1.0) as u32;
+			let done = strip.clone();
+
+			thread::spawn(move || {
+				let thumbnails = (0..count)
+					.filter_map(|i| decode(&path, length * (i as f32 + 0.5) / count as f32, width))
+					.collect();
+
+				drop(done.set(thumbnails));
+			});
+
+			strip
+		});
+
+		if strip.get().is_some() {
+			return self.0[&(source.path.clone(), count)].get().map(Vec::as_slice);
+		}
+
+		self.0.iter()
+			.filter(|((path, _), _)| *path == source.path)
+			.filter_map(|((_, count), strip)| Some((count, strip.get()?)))
+			.max_by_key(|&(count, _)| count)
+			.map(|(_, strip)| strip.as_slice())
+	}
+}
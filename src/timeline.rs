@@ -0,0 +1,52 @@
+/// Pixels per second of the timeline before it's zoomed
+const DEFAULT_ZOOM: f32 = 10.0;
+/// Closest the timeline can be zoomed out, in pixels per second
+const MIN_ZOOM: f32 = 0.1;
+/// Furthest the timeline can be zoomed in, in pixels per second
+const MAX_ZOOM: f32 = 1000.0;
+
+/// Part of the project shown in the timeline, which both drawing clips and mapping the cursor to a time go through
+pub struct TimelineView {
+	/// Time shown at the left edge of the timeline
+	pub start: f32,
+	pub pixels_per_second: f32
+}
+
+impl Default for TimelineView {
+	fn default() -> Self {
+		Self {
+			start: 0.0,
+			pixels_per_second: DEFAULT_ZOOM
+		}
+	}
+}
+
+impl TimelineView {
+	/// Returns where `time` is shown on a timeline whose left edge is at `left`
+	pub fn x(&self, time: f32, left: f32) -> f32 {
+		(time - self.start).mul_add(self.pixels_per_second, left)
+	}
+
+	/// Returns the time shown at `x` on a timeline whose left edge is at `left`
+	pub fn time(&self, x: f32, left: f32) -> f32 {
+		(x - left) / self.pixels_per_second + self.start
+	}
+
+	/// Converts a distance in pixels to a length of time
+	pub fn seconds(&self, pixels: f32) -> f32 {
+		pixels / self.pixels_per_second
+	}
+
+	/// Multiplies the zoom by `factor`, keeping the time shown at `x` in place
+	pub fn zoom_at(&mut self, factor: f32, x: f32, left: f32) {
+		let anchor = self.time(x, left);
+
+		self.pixels_per_second = (self.pixels_per_second * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+		self.start = (anchor - self.seconds(x - left)).max(0.0);
+	}
+
+	/// Scrolls the timeline by `pixels`, where positive values move it's contents to the right
+	pub fn pan(&mut self, pixels: f32) {
+		self.start = (self.start - self.seconds(pixels)).max(0.0);
+	}
+}
@@ -1,7 +1,10 @@
-use std::{cmp::Ordering, path::PathBuf, num::NonZeroU16, ops::RangeInclusive};
+use std::{cmp::Ordering, fmt::{self, Display, Formatter}, io, path::{Path, PathBuf}, num::NonZeroU32, ops::RangeInclusive, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering::Relaxed}, mpsc::{sync_channel, Receiver, TryRecvError}}, thread};
 
-use ffmpeg_sidecar::{child::FfmpegChild, event::OutputVideoFrame, command::FfmpegCommand};
-use tiny_skia::{IntSize, Pixmap};
+use ffmpeg_sidecar::{child::FfmpegChild, event::{FfmpegEvent, LogLevel, OutputVideoFrame}, iter::FfmpegIterator};
+use serde::{Deserialize, Serialize};
+use tiny_skia::{BlendMode, Color, IntRect, IntSize, Pixmap, Rect};
+
+use crate::{audio::MAX_VOLUME, cache::FrameCache, ffmpeg, settings::Corner, source::{is_url, video_stream, Source, Sources}};
 
 /// Defines in what way a [`Video`] is being manipulated by the user (scale, translate, etc.)
 #[derive(PartialEq, Eq)]
@@ -14,160 +17,1798 @@ pub enum Drag {
 	None
 }
 
+/// Starts `FFmpeg` decoding a file's video stream into raw frames on it's stdout
+///
+/// Without a `pix_fmt`, frames are output in whichever pixel format `FFmpeg` picks for the source
+///
+/// The `filter` is applied to the source's frames before they're scaled to `size`
+///
+/// With a `seek`, decoding starts at that many seconds into the source, as precisely as the [`SeekMode`] allows
+///
+/// Only the `stream`th video stream of the source is decoded
+///
+/// With a `readrate`, the source is read at most that many times faster than real time
+///
+/// Without a number of `threads`, `FFmpeg` decodes with as many as it sees fit
+#[allow(clippy::too_many_arguments)] // Each sets an independent `FFmpeg` option
+fn spawn_decoder(path: &Path, stream: usize, hwaccel: &str, pix_fmt: Option<&str>, seek: Option<(f32, SeekMode)>, readrate: Option<f32>, size: Option<(u32, u32)>, filter: Option<&str>, threads: Option<usize>) -> Result<FfmpegChild, VideoError> {
+	let mut command = ffmpeg::command();
+
+	command
+		.hide_banner()
+		.create_no_window()
+		.no_audio()
+		.args(["-sn", "-dn"])
+		.hwaccel(hwaccel);
+
+	if let Some((seek, mode)) = seek {
+		if mode == SeekMode::Fast {
+			command.arg("-noaccurate_seek");
+		}
+
+		command.seek(seek.to_string());
+	}
+
+	if let Some(readrate) = readrate {
+		command.readrate(readrate);
+	}
+
+	if is_url(path) {
+		command.args(["-rw_timeout", NETWORK_TIMEOUT]);
+	}
+
+	if let Some(threads) = threads {
+		command.args(["-threads", &threads.to_string()]);
+	}
+
+	command
+		.input(path.to_string_lossy())
+		.args(["-map", &format!("0:v:{stream}")])
+		.args(["-fps_mode", "cfr"]) // Variable frame rate sources are evened out, so that frame numbers follow presentation timestamps
+		.format("rawvideo");
+
+	if let Some(filter) = filter {
+		command.filter(filter);
+	}
+
+	if let Some(pix_fmt) = pix_fmt {
+		command.pix_fmt(pix_fmt);
+	}
+
+	if let Some((width, height)) = size {
+		command.size(width, height);
+	}
+
+	command
+		.no_overwrite()
+		.pipe_stdout()
+		.spawn().map_err(VideoError::Spawn)
+}
+
+/// Longest `FFmpeg` waits on a network stream before giving up, in microseconds, so that a dropped connection ends the clip rather than stalling it forever
+const NETWORK_TIMEOUT: &str = "10000000";
+
+/// Largest number of frames a [`Video`] can step back by before it's decoded directly from the new frame, rather than from a bit earlier to fill the cache
+const SMALL_SEEK_FRAMES: u32 = 15;
+
+/// Memory which each [`Video`] may use for recently shown frames
+const FRAME_CACHE_BYTES: usize = 128 * 1024 * 1024;
+
+/// How much faster than it's shown the source is read while playing, so that `FFmpeg` can catch up after falling behind
+const READRATE_HEADROOM: f32 = 2.0;
+
+/// Memory which each [`Video`] may use for frames decoded ahead of the playhead
+const PREBUFFER_BYTES: usize = 64 * 1024 * 1024;
+
+/// Turns an `FFmpeg` instance's events into it's frames, printing any errors it reports and keeping the last one in `last_error`
+///
+/// Frames are decoded ahead on a separate thread, buffering as many frames of `size` as fit in [`PREBUFFER_BYTES`], so that a slow `FFmpeg` doesn't stall playback until the buffer runs dry
+///
+/// The thread stops once the returned [`Receiver`] is dropped, so frames still being decoded for a position that was seeked away from are thrown out
+fn frames(iter: FfmpegIterator, path: &Path, last_error: Arc<Mutex<Option<String>>>, (width, height): (u32, u32)) -> Receiver<OutputVideoFrame> {
+	let path = path.display().to_string();
+
+	let frames = iter.filter_map(move |event| match event {
+		FfmpegEvent::OutputFrame(frame) => Some(frame),
+		FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, error) | FfmpegEvent::Error(error) => {
+			log::error!("FFmpeg error while decoding {path}: {error}");
+			*last_error.lock().unwrap() = Some(error);
+			None
+		},
+		_ => None
+	});
+
+	let capacity = (PREBUFFER_BYTES / (width as usize * height as usize * 4).max(1)).clamp(1, 30);
+	let (sender, receiver) = sync_channel(capacity);
+
+	// Stops once the buffer is dropped, which happens when the `Video` seeks or is removed
+	thread::spawn(move || {
+		for frame in frames {
+			if sender.send(frame).is_err() {
+				break;
+			}
+		}
+	});
+
+	receiver
+}
+
+/// Returns the number of the frame shown `time` seconds into a source running at `fps`, counting from `0`
+///
+/// This holds for variable frame rate sources too, since [`spawn_decoder`] has `FFmpeg` output them at a constant frame rate, repeating or dropping frames according to their presentation timestamps
+fn frame_at(time: f32, fps: f32) -> u32 {
+	(time * fps).round() as u32
+}
+
+/// Returns how many seconds into a source running at `fps` it's frame `num` is shown, which is where `FFmpeg` seeks to for it
+///
+/// This is the inverse of [`frame_at`], so that decoding started at a frame is counted from that same frame
+fn frame_time(num: u32, fps: f32) -> f32 {
+	num as f32 / fps
+}
+
+/// Converts planar 4:2:0 YUV with limited range BT.601 colors, which `FFmpeg` also assumes when converting it, into RGBA
+fn yuv420p_to_rgba(data: &[u8], width: usize, height: usize) -> Option<Vec<u8>> {
+	let planes = width * height;
+	let luma = data.get(..planes)?;
+	let u = data.get(planes..planes + planes / 4)?;
+	let v = data.get(planes + planes / 4..)?;
+
+	let mut rgba = Vec::with_capacity(width * height * 4);
+
+	for row in 0..height {
+		for column in 0..width {
+			let chroma = (row / 2) * (width / 2) + column / 2;
+
+			let y = 298 * (i32::from(luma[row * width + column]) - 16);
+			let u = i32::from(*u.get(chroma)?) - 128;
+			let v = i32::from(*v.get(chroma)?) - 128;
+
+			let channel = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+
+			rgba.extend([
+				channel(y + 409 * v),
+				channel(y - 100 * u - 208 * v),
+				channel(y + 516 * u),
+				u8::MAX
+			]);
+		}
+	}
+
+	Some(rgba)
+}
+
+/// Turns a decoded frame into a [`Pixmap`], converting it to RGBA if it was decoded in another of the source's pixel formats
+///
+/// `FFmpeg` outputs straight alpha, which is premultiplied for `tiny-skia`, so that transparent sources composite correctly
+fn to_pixmap(mut frame: OutputVideoFrame) -> Option<Pixmap> {
+	match frame.pix_fmt.as_str() {
+		"rgba" => {},
+		"bgra" => frame.data.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2)),
+		"rgb24" => frame.data = frame.data.chunks_exact(3).flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX]).collect(),
+		"yuv420p" => frame.data = yuv420p_to_rgba(&frame.data, frame.width as usize, frame.height as usize)?,
+		_ => return None
+	}
+
+	for pixel in frame.data.chunks_exact_mut(4).filter(|pixel| pixel[3] < u8::MAX) {
+		let alpha = u16::from(pixel[3]);
+
+		for channel in &mut pixel[..3] {
+			*channel = ((u16::from(*channel) * alpha + 127) / 255) as u8;
+		}
+	}
+
+	Pixmap::from_vec(frame.data, IntSize::from_wh(frame.width, frame.height)?)
+}
+
+/// Reason why a [`Video`] couldn't be decoded
+pub enum VideoError {
+	/// `FFmpeg` couldn't be started
+	Spawn(io::Error),
+	/// `FFmpeg` started, but reported an error
+	Ffmpeg(String),
+	/// The file has no video stream with a frame rate
+	NotVideo,
+	/// The video stream has a width or height of zero
+	ZeroSize
+}
+
+impl Display for VideoError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Spawn(error) => write!(f, "FFmpeg couldn't be started: {error}"),
+			Self::Ffmpeg(error) => write!(f, "FFmpeg failed: {error}"),
+			Self::NotVideo => write!(f, "No video stream was found"),
+			Self::ZeroSize => write!(f, "The video reports a width or height of zero")
+		}
+	}
+}
+
+/// Frame rate above which a source counts as high frame rate footage, which may be played back as slow motion
+pub const HIGH_FPS: f32 = 60.0;
+
+/// Length in seconds which images are shown for when added
+pub const STILL_LENGTH: f32 = 5.0;
+
+/// Frame rate at which slow motion [`Video`]s are played back, showing every source frame
+const SLOW_MOTION_FPS: f32 = 30.0;
+
+/// Source of unique [`Video`] IDs, which stay stable while other [`Video`]s are added and removed
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Defines what a [`Video`] shows after it's source has run out of frames
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndBehavior {
+	Black,
+	Hold,
+	Loop
+}
+
+impl EndBehavior {
+	/// Returns the next [`EndBehavior`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::Black => Self::Hold,
+			Self::Hold => Self::Loop,
+			Self::Loop => Self::Black
+		}
+	}
+}
+
+/// Fraction of a [`Video`]'s display resolution which it's frames are decoded at, trading sharpness for decoding speed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DecodeQuality {
+	Full,
+	Half,
+	Quarter
+}
+
+impl DecodeQuality {
+	/// Returns the next [`DecodeQuality`], for cycling through them
+	pub const fn next(self) -> Self {
+		match self {
+			Self::Full => Self::Half,
+			Self::Half => Self::Quarter,
+			Self::Quarter => Self::Full
+		}
+	}
+
+	/// Returns the number the display resolution is divided by
+	const fn divisor(self) -> u32 {
+		match self {
+			Self::Full => 1,
+			Self::Half => 2,
+			Self::Quarter => 4
+		}
+	}
+}
+
+/// How precisely `FFmpeg` seeks when a [`Video`] jumps back or restarts decoding
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+	/// Starts at the keyframe before the requested frame, which is quick but may be off by up to the keyframe interval
+	Fast,
+	/// Decodes from the keyframe before the requested frame and discards frames up to it
+	Exact
+}
+
+/// Defines how a [`Video`] is sized to the canvas, regardless of it's aspect ratio
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleMode {
+	/// Covers the canvas exactly, distorting the [`Video`] if the aspect ratios differ
+	Stretch,
+	/// Fits inside the canvas, leaving bars along two edges if the aspect ratios differ
+	Fit,
+	/// Covers the canvas, cutting off two edges if the aspect ratios differ
+	Fill
+}
+
+impl ScaleMode {
+	/// Returns the next [`ScaleMode`], where `None` lets the [`Video`] be sized freely, for cycling through them
+	pub const fn next(mode: Option<Self>) -> Option<Self> {
+		match mode {
+			None => Some(Self::Fit),
+			Some(Self::Fit) => Some(Self::Fill),
+			Some(Self::Fill) => Some(Self::Stretch),
+			Some(Self::Stretch) => None
+		}
+	}
+}
+
+/// Position and display size of a [`Video`] at a point in time, between which it's transform is interpolated
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+	/// Seconds since the start of the [`Video`], so that keyframes move along with it on the timeline
+	pub time: f32,
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32
+}
+
+impl Keyframe {
+	/// Linearly interpolates between two [`Keyframe`]s at a time between theirs
+	fn lerp(self, other: Self, time: f32) -> Self {
+		let t = if other.time > self.time { ((time - self.time) / (other.time - self.time)).clamp(0.0, 1.0) } else { 0.0 };
+		let lerp = |a: f32, b: f32| (b - a).mul_add(t, a);
+
+		Self {
+			time,
+			x: lerp(self.x, other.x),
+			y: lerp(self.y, other.y),
+			width: lerp(self.width, other.width),
+			height: lerp(self.height, other.height)
+		}
+	}
+}
+
+/// Color adjustments applied to a [`Video`] with `FFmpeg`'s `eq` filter, which leave it unchanged at their defaults
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Grade {
+	/// Added to the brightness, from `-1.0` to `1.0`
+	pub brightness: f32,
+	/// Factor contrast is multiplied by, from `0.0` to `2.0`
+	pub contrast: f32,
+	/// Factor saturation is multiplied by, from `0.0`, which is grayscale, to `3.0`
+	pub saturation: f32
+}
+
+impl Default for Grade {
+	fn default() -> Self {
+		Self {
+			brightness: 0.0,
+			contrast: 1.0,
+			saturation: 1.0
+		}
+	}
+}
+
+impl Grade {
+	/// Limits each adjustment to it's range, resetting those that aren't finite
+	fn clamped(self) -> Self {
+		let neutral = Self::default();
+		let clamp = |value: f32, neutral: f32, max: f32| if value.is_finite() { value.clamp(neutral - 1.0, max) } else { neutral };
+
+		Self {
+			brightness: clamp(self.brightness, neutral.brightness, 1.0),
+			contrast: clamp(self.contrast, neutral.contrast, 2.0),
+			saturation: clamp(self.saturation, neutral.saturation, 3.0)
+		}
+	}
+
+	/// Creates the `eq` filter applying the [`Grade`], unless it's neutral
+	///
+	/// `eq` only handles formats without alpha, so grading a source drops it's transparency
+	fn filter(self) -> Option<String> {
+		(self != Self::default()).then(|| format!("eq=brightness={}:contrast={}:saturation={}", self.brightness, self.contrast, self.saturation))
+	}
+}
+
+/// Blend modes which a [`Video`] can be cycled through, starting with the default
+///
+/// `Source` comes last so that the indices saved in older projects keep their meaning, with the old default of `Source` becoming `SourceOver`, which looks the same for sources without transparency
+const BLEND_MODES: [BlendMode; 9] = [
+	BlendMode::SourceOver,
+	BlendMode::Multiply,
+	BlendMode::Screen,
+	BlendMode::Plus,
+	BlendMode::Overlay,
+	BlendMode::Darken,
+	BlendMode::Lighten,
+	BlendMode::Difference,
+	BlendMode::Source
+];
+
+/// Everything the user has set on a [`Video`], from which it can be recreated
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)] // Independent toggles set by the user
+pub struct VideoState {
+	pub path: PathBuf,
+	pub start: f32,
+	pub end: f32,
+	pub x: i32,
+	pub y: i32,
+	/// Resolution frames are decoded at
+	pub size: (u32, u32),
+	pub slow_motion: bool,
+	pub end_behavior: EndBehavior,
+	pub opacity: f32,
+	/// Index into [`BLEND_MODES`]
+	pub blend_mode: usize,
+	#[serde(default)]
+	pub grade: Grade,
+	/// `x`, `y`, `width` and `height` of the crop, from `0.0` to `1.0`
+	pub crop: Option<(f32, f32, f32, f32)>,
+	pub rotation: u8,
+	pub flip_horizontal: bool,
+	pub flip_vertical: bool,
+	#[serde(default)]
+	pub track: u8,
+	#[serde(default = "default_rate")]
+	pub rate: f32,
+	#[serde(default = "default_still_length")]
+	pub still_length: f32,
+	#[serde(default)]
+	pub scale_mode: Option<ScaleMode>,
+	#[serde(default)]
+	pub fade_in: f32,
+	#[serde(default)]
+	pub fade_out: f32,
+	#[serde(default)]
+	pub keyframes: Vec<Keyframe>,
+	#[serde(default)]
+	pub shift: f32,
+	#[serde(default)]
+	pub trim_end: Option<f32>,
+	#[serde(default)]
+	pub muted: bool,
+	#[serde(default)]
+	pub solo: bool,
+	#[serde(default = "default_volume")]
+	pub volume: f32,
+	#[serde(default)]
+	pub stream: usize
+}
+
+/// Playback rate of [`VideoState`]s saved before rates could be changed
+const fn default_rate() -> f32 {
+	1.0
+}
+
+/// Volume of [`VideoState`]s saved before clips could be turned up or down
+const fn default_volume() -> f32 {
+	1.0
+}
+
+/// Length of [`VideoState`]s saved before images could be added
+const fn default_still_length() -> f32 {
+	STILL_LENGTH
+}
+
 /// Contains metadata about a specific video as well as the `FFmpeg` instance, iterator and functions required to load frames
+#[allow(clippy::struct_excessive_bools)] // Independent toggles set by the user
 pub struct Video {
-	pub frame: Option<Pixmap>,
+	pub id: u64,
+	/// Frame currently shown, which is shared with the `cache` rather than copied into it
+	pub frame: Option<Arc<Pixmap>>,
 	pub x: i32,
 	pub y: i32,
 	pub scale: Option<(f32, f32)>,
 	pub drag: Drag,
-	in_width: NonZeroU16,
-	in_height: NonZeroU16,
+	/// Opacity from `0.0` to `1.0` with which the [`Video`] is drawn over the ones beneath it
+	pub opacity: f32,
+	/// How the [`Video`] is composited with the ones beneath it
+	pub blend_mode: BlendMode,
+	/// Layer the [`Video`] is drawn on, with higher tracks drawn over lower ones
+	pub track: u8,
+	/// Playback rate relative to the playhead, which is applied on top of slow motion
+	pub rate: f32,
+	/// Length in seconds the [`Video`] is shown for if it's source is an image
+	pub still_length: f32,
+	/// How the [`Video`] is sized to the canvas whenever it changes size, if at all
+	pub scale_mode: Option<ScaleMode>,
+	/// Seconds over which the [`Video`] fades in from transparent at it's start
+	pub fade_in: f32,
+	/// Seconds over which the [`Video`] fades out to transparent at it's end
+	pub fade_out: f32,
+	/// Factor `opacity` is multiplied by from fading in or out at the last loaded timestamp
+	fade: f32,
+	/// Transforms the [`Video`] is animated between, sorted by time, which override it's position while there are any
+	pub keyframes: Vec<Keyframe>,
+	/// Display size interpolated from the `keyframes` at the last loaded timestamp
+	animated_size: Option<(f32, f32)>,
+	quality: DecodeQuality,
+	/// How much larger than it's display size the [`Video`] is rendered, such as for exports at a higher resolution than the project is laid out at, which frames are decoded at so that they aren't upscaled
+	render_scale: f32,
+	seek_mode: SeekMode,
+	/// Whether `FFmpeg` last seeked with [`SeekMode::Fast`], so that frame numbers may be off and decoded frames are kept out of the `cache`
+	approximate: bool,
+	/// Hardware acceleration backend `FFmpeg` decodes with, which is `none` if the chosen one didn't work
+	hwaccel: &'static str,
+	/// Speed the project is playing at, if it's playing, which limits how fast `FFmpeg` reads the source
+	playback_speed: Option<f32>,
+	/// Number of threads `FFmpeg` decodes with, if limited
+	threads: Option<usize>,
+	/// Part of the source which is decoded, from `0.0` to `1.0` of it's width and height
+	crop: Option<Rect>,
+	/// Number of quarter turns clockwise the [`Video`] is rotated by, applied after flipping
+	rotation: u8,
+	grade: Grade,
+	flip_horizontal: bool,
+	flip_vertical: bool,
+	in_width: NonZeroU32,
+	in_height: NonZeroU32,
 	pub ffmpeg: FfmpegChild,
 	pub duration: RangeInclusive<f32>,
+	/// Time on the timeline at which the first frame of the source would be shown, which is where the [`Video`] starts unless it's head has been cut off
+	pub shift: f32,
+	/// Time on the timeline at which the [`Video`] is cut off before it's source ends, such as after splitting it
+	pub trim_end: Option<f32>,
+	/// Whether the [`Video`]'s audio is left out of playback
+	pub muted: bool,
+	/// Whether the [`Video`]'s audio is played while the audio of every [`Video`] that isn't soloed is left out
+	pub solo: bool,
+	/// Factor the [`Video`]'s audio samples are multiplied by before mixing, where `1.0` leaves them unchanged
+	pub volume: f32,
+	/// Index of the source's video stream which is decoded, below `Source.video_streams`
+	pub stream: usize,
 
-	path: PathBuf,
+	pub source: Arc<Source>,
+	/// Whether high frame rate footage plays every frame over a longer time span, rather than skipping frames to play in real time
+	pub slow_motion: bool,
+	pub end_behavior: EndBehavior,
+	/// Set if decoding failed after the [`Video`] was created, in which case a placeholder is shown instead of frames
+	pub error: Option<VideoError>,
+	/// Time at which frames ran out before the source's detected length, which then becomes the end of the [`Video`]
+	cut_short: Option<f32>,
+	/// Number of the next frame `iter` will return, minus one
 	frame_num: u32,
-	fps: f32,
-	iter: Box<dyn Iterator<Item = OutputVideoFrame> + Send>
+	/// Number of the frame in the `frame` field, which can be behind `frame_num` when it came from the `cache`
+	shown_num: u32,
+	cache: FrameCache,
+	/// Frames decoded ahead by `FFmpeg`, which are waited for unless the [`Video`] is playing
+	iter: Receiver<OutputVideoFrame>,
+	/// Last error reported by `FFmpeg`, used to tell a failed decoder apart from one that reached the end of the source
+	ffmpeg_error: Arc<Mutex<Option<String>>>,
+	/// Whether `ffmpeg` was quit to free up it's slot in the decoder budget, in which case it's restarted on the next frame that has to be decoded
+	suspended: bool
 }
 
 impl Video {
 	/// Creates a new [`Video`] from a path and calls `load()` on it's first frame
-	pub fn new(path: PathBuf, start: f32) -> Option<Self> {
-		let mut ffmpeg = FfmpegCommand::new()
-			.hide_banner()
-			.create_no_window()
-			.no_audio()
-			.args(["-sn", "-dn"])
-			.hwaccel("auto")
-			.input(path.to_str().unwrap())
-			.format("rawvideo")
-			.pix_fmt("rgba")
-			.no_overwrite()
-			.pipe_stdout()
-			.spawn().unwrap();
-
-		let mut iter = ffmpeg.iter().unwrap();
-
-		let metadata = iter.collect_metadata().unwrap();
-		let stream = metadata.output_streams.first()?;
-
-		let fps = stream.fps;
-
-		if stream.stream_type.as_str() != "Video" || fps == 0.0 {
-			print!("failed");
-			return None;
+	///
+	/// The file's [`Source`] is taken from `sources` if another [`Video`] has already loaded it
+	///
+	/// If `FFmpeg` fails with the chosen hardware acceleration backend, decoding is retried once without it
+	pub fn new(path: PathBuf, start: f32, sources: &mut Sources) -> Result<Self, VideoError> {
+		let hwaccel = ffmpeg::hwaccel();
+
+		match Self::with_hwaccel(path.clone(), start, sources, hwaccel) {
+			Err(VideoError::Ffmpeg(error)) if hwaccel != "none" => {
+				log::warn!("Decoding {} with hardware acceleration backend {hwaccel} failed ({error}), retrying with none", path.display());
+
+				let video = Self::with_hwaccel(path, start, sources, "none");
+
+				if video.is_ok() {
+					log::info!("Decoding with hardware acceleration backend none worked");
+				}
+
+				video
+			},
+			video => video
+		}
+	}
+
+	/// Creates a new [`Video`] decoded using a specific hardware acceleration backend
+	fn with_hwaccel(path: PathBuf, start: f32, sources: &mut Sources, hwaccel: &'static str) -> Result<Self, VideoError> {
+		if let Some(error) = sources.rejected(&path) {
+			return Err(error);
 		}
 
-		Some(Self {
-			in_width: NonZeroU16::new(stream.width as u16)?,
-			in_height: NonZeroU16::new(stream.height as u16)?,
+		let cached = sources.get(&path);
+
+		// Unless the source is already known, `FFmpeg` is left to pick the pixel format it decodes to
+		let mut ffmpeg = spawn_decoder(&path, 0, hwaccel, cached.as_ref().map(|source| source.pix_fmt), None, None, None, None, None)?;
+
+		let source = ffmpeg.iter()
+			.map_err(|error| VideoError::Ffmpeg(error.to_string()))
+			.and_then(|mut iter| {
+				let (source, native) = if let Some(source) = cached {
+					(source, true)
+				} else {
+					let metadata = iter.collect_metadata().map_err(|error| VideoError::Ffmpeg(error.to_string()))?;
+					let native = video_stream(&metadata).map(|stream| stream.pix_fmt.clone());
+					let source = sources.insert(path, &metadata)?;
+					let native = native.as_deref() == Some(source.pix_fmt);
+
+					(source, native)
+				};
+
+				Ok((source, iter, native))
+			});
+
+		let (source, iter) = match source {
+			Ok((source, iter, true)) => (source, iter),
+			Ok((source, _, false)) => {
+				// The pixel format `FFmpeg` picked can't be drawn, so decoding has to be restarted with a supported one
+				drop(ffmpeg.quit());
+
+				ffmpeg = spawn_decoder(&source.path, 0, hwaccel, Some(source.pix_fmt), None, None, None, None, None)?;
+				let iter = ffmpeg.iter().map_err(|error| VideoError::Ffmpeg(error.to_string()))?;
+
+				(source, iter)
+			},
+			Err(error) => {
+				drop(ffmpeg.quit());
+				return Err(error);
+			}
+		};
+
+		let ffmpeg_error: Arc<Mutex<Option<String>>> = Arc::default();
+		let iter = frames(iter, &source.path, ffmpeg_error.clone(), (source.width.get(), source.height.get()));
+
+		Ok(Self {
+			id: NEXT_ID.fetch_add(1, Relaxed),
+			in_width: source.width,
+			in_height: source.height,
 			frame: None,
-			path,
+			source,
+			slow_motion: false,
+			end_behavior: EndBehavior::Black,
+			error: None,
+			cut_short: None,
 			frame_num: 1, // To make the video reload() on first frame
-			fps,
+			shown_num: u32::MAX,
+			cache: FrameCache::new(FRAME_CACHE_BYTES),
 			duration: start..=start,
+			shift: start,
+			trim_end: None,
+			muted: false,
+			solo: false,
+			volume: 1.0,
+			stream: 0,
+			grade: Grade::default(),
 			x: 0,
 			y: 0,
 			scale: None,
 			drag: Drag::None,
+			opacity: 1.0,
+			blend_mode: BLEND_MODES[0],
+			track: 0,
+			rate: 1.0,
+			still_length: STILL_LENGTH,
+			scale_mode: None,
+			fade_in: 0.0,
+			fade_out: 0.0,
+			fade: 1.0,
+			keyframes: Vec::new(),
+			animated_size: None,
+			quality: DecodeQuality::Full,
+			render_scale: 1.0,
+			seek_mode: SeekMode::Exact,
+			approximate: false,
+			hwaccel,
+			playback_speed: None,
+			threads: None,
+			crop: None,
+			rotation: 0,
+			flip_horizontal: false,
+			flip_vertical: false,
 			ffmpeg,
-			iter: Box::new(iter.filter_frames())
+			iter,
+			ffmpeg_error,
+			suspended: false
 		})
 	}
 
 	/// Requests for the [`Video`] to load a new frame into it's `frame` field
 	///
 	/// * If the frame has the same timestamp as the last frame, nothing is changed
-	/// * If it has a larger timestamp, `Video.iter` will advance until it reaches that timestamp
-	/// * If it has a smaller timestamp, `reload()` is called on the [`Video`] and it's `ffmpeg`, `iter` and `frame` are replaced by ones starting at the requested timestamp
+	/// * If it has a larger timestamp, `Video.iter` will advance until it reaches that timestamp, or while playing, until it runs out of decoded frames, in which case the frame is shown once it's ready
+	/// * If it has a smaller timestamp and was shown recently, it's taken from the `cache`
+	/// * Otherwise, if it has a smaller timestamp, `reload()` is called on the [`Video`] and it's `ffmpeg`, `iter` and `frame` are replaced by ones starting at the requested timestamp
 	pub fn load(&mut self, timestamp: f32) {
+		self.fade = self.fade_at(timestamp);
+
+		// Clips being dragged stay where they're dragged to, until the move is recorded in their keyframes
+		if self.drag == Drag::None {
+			self.animate(timestamp);
+		}
+
+		if self.error.is_some() {
+			return; // Keep showing the placeholder
+		}
+
 		let time = timestamp; // - self.duration.start();
 
 		if time >= 0.0 {
-			let num = (timestamp * self.fps).round() as u32;
+			// Images are decoded once and then shown for as long as the clip lasts, and cut off videos only until they're cut off
+			let hidden = timestamp < *self.duration.start() || self.trim_end.is_some_and(|end| timestamp > end);
+
+			if hidden || (self.source.is_image && !self.is_active(timestamp)) {
+				self.frame = None;
+				self.shown_num = u32::MAX;
+				return;
+			}
+
+			let num = if self.source.is_image {
+				0
+			} else {
+				frame_at(self.source_time(timestamp), self.source.fps)
+			};
+
+			if num == self.shown_num {
+				return;
+			}
+
+			// Frames behind `iter` have to be cached, or `FFmpeg` has to seek back to them
+			if num <= self.frame_num {
+				if let Some(frame) = self.cache.get(num) {
+					self.frame = Some(frame);
+					self.shown_num = num;
+					return;
+				}
+			}
+
+			// A suspended `FFmpeg` is restarted at the requested frame, as if seeking to it
+			if self.suspended {
+				self.suspended = false;
+				self.frame_num = num;
+			}
 
 			match num.cmp(&self.frame_num) {
 				Ordering::Greater => {
-					let diff = num - self.frame_num;
+					let mut new_frame = None;
+					let mut ended = false;
 
-					let new_frame = if diff == 1 {
-						self.iter.next()
-					} else {
-						self.iter.nth((diff - 1) as usize)
-					};
+					// While playing, a stalled `FFmpeg` only delays this `Video` rather than freezing the whole window
+					while self.frame_num < num {
+						let frame = if self.playback_speed.is_some() {
+							match self.iter.try_recv() {
+								Ok(frame) => Ok(frame),
+								Err(TryRecvError::Empty) => break, // Shown once it's been decoded
+								Err(TryRecvError::Disconnected) => Err(())
+							}
+						} else {
+							self.iter.recv().map_err(drop)
+						};
+
+						let Ok(frame) = frame else {
+							ended = true;
+							break;
+						};
+
+						self.frame_num += 1;
+						new_frame = Some(frame);
+					}
 
 					if let Some(new_frame) = new_frame {
-						self.frame = Pixmap::from_vec(new_frame.data, IntSize::from_wh(new_frame.width, new_frame.height).unwrap());
+						self.show(self.frame_num, new_frame);
 
 						// This is not good
 						if timestamp > *self.duration.end() {
 							self.duration = *self.duration.start()..=timestamp;
 						}
-					} else {
-						self.frame = None;
+					}
+
+					if !ended {
+						return;
+					}
+
+					if let Some(error) = self.take_ffmpeg_error() {
+						self.fail(VideoError::Ffmpeg(error));
+					} else if self.end_behavior == EndBehavior::Black {
+						// Running out of frames before the detected length means decoding failed, so the clip is ended early
+						let cut_short = self.source.length.is_some_and(|length| self.source_time(timestamp) < length - self.source.fps.recip());
+
+						if cut_short {
+							self.cut_short = Some(self.cut_short.map_or(timestamp, |cut_short| cut_short.min(timestamp)));
+						}
+
+						// Debug builds point out the failure instead of hiding it
+						self.frame = if cut_short && cfg!(debug_assertions) {
+							self.placeholder()
+						} else {
+							None
+						};
+					} else if timestamp > *self.duration.end() {
+						// Still showing the held frame
+						self.duration = *self.duration.start()..=timestamp;
 					}
 
 					self.frame_num = num;
 				},
-				Ordering::Less => {
+				// Live streams can't go back, so the last frame is held until the playhead passes it again
+				Ordering::Less if self.source.is_live => return,
+				Ordering::Less | Ordering::Equal => {
 					// SKIP THIS IF TIMESTAMP IS OUTSIDE VIDEO
 
-					self.frame_num = num;
+					// Small steps back start decoding a bit earlier, filling the `cache` so that the next few steps don't have to restart `FFmpeg`
+					let start = if self.frame_num - num <= SMALL_SEEK_FRAMES {
+						num.saturating_sub(SMALL_SEEK_FRAMES)
+					} else {
+						num
+					};
+
+					self.frame_num = start;
 
 					self.reload();
 
-					if let Some(new_frame) = self.iter.next() {
-						self.frame = Pixmap::from_vec(new_frame.data, IntSize::from_wh(new_frame.width, new_frame.height).unwrap());
+					if self.error.is_some() {
+						return;
+					}
+
+					let mut new_frame = self.iter.recv().ok();
+
+					for skipped in start..num {
+						let Some(frame) = new_frame.take().and_then(to_pixmap) else { break };
+
+						self.cache.insert(skipped, Arc::new(frame));
+						new_frame = self.iter.recv().ok();
+					}
+
+					self.frame_num = num;
+
+					if let Some(new_frame) = new_frame {
+						self.show(num, new_frame);
+					} else if let Some(error) = self.take_ffmpeg_error() {
+						self.fail(VideoError::Ffmpeg(error));
 					} else {
 						self.frame = None;
 					}
-				},
-				Ordering::Equal => ()
+				}
 			}
+
+			self.shown_num = num;
 		} else {
 			self.frame = None;
 			self.frame_num = 0;
+			self.shown_num = u32::MAX;
+		}
+	}
+
+	/// Replaces the [`Video`]'s `frame` with a newly decoded one, sharing it with the `cache`
+	fn show(&mut self, num: u32, frame: OutputVideoFrame) {
+		self.frame = to_pixmap(frame).map(Arc::new);
+
+		if let Some(frame) = self.frame.as_ref().filter(|_| !self.approximate) {
+			self.cache.insert(num, frame.clone());
+		}
+	}
+
+	/// Recreates a [`Video`] from it's [`VideoState`]
+	pub fn from_state(state: &VideoState, sources: &mut Sources) -> Result<Self, VideoError> {
+		let mut video = Self::new(state.path.clone(), state.start, sources)?;
+		video.apply(state);
+
+		Ok(video)
+	}
+
+	/// Returns the [`VideoState`] from which the [`Video`] can be recreated
+	pub fn state(&self) -> VideoState {
+		VideoState {
+			path: self.source.path.clone(),
+			start: *self.duration.start(),
+			end: *self.duration.end(),
+			x: self.x,
+			y: self.y,
+			size: (self.in_width.get(), self.in_height.get()),
+			slow_motion: self.slow_motion,
+			end_behavior: self.end_behavior,
+			opacity: self.opacity,
+			blend_mode: BLEND_MODES.iter().position(|&mode| mode == self.blend_mode).unwrap_or(0),
+			grade: self.grade,
+			crop: self.crop.map(|crop| (crop.x(), crop.y(), crop.width(), crop.height())),
+			rotation: self.rotation,
+			flip_horizontal: self.flip_horizontal,
+			flip_vertical: self.flip_vertical,
+			track: self.track,
+			rate: self.rate,
+			still_length: self.still_length,
+			scale_mode: self.scale_mode,
+			fade_in: self.fade_in,
+			fade_out: self.fade_out,
+			keyframes: self.keyframes.clone(),
+			shift: self.shift,
+			trim_end: self.trim_end,
+			muted: self.muted,
+			solo: self.solo,
+			volume: self.volume,
+			stream: self.stream
+		}
+	}
+
+	/// Applies a [`VideoState`] to the [`Video`], reloading it if that changes how it's decoded
+	pub fn apply(&mut self, state: &VideoState) {
+		self.duration = state.start..=state.end.max(state.start);
+		self.shift = state.shift;
+		self.trim_end = state.trim_end;
+		self.muted = state.muted;
+		self.solo = state.solo;
+		self.volume = state.volume.clamp(0.0, MAX_VOLUME);
+		self.x = state.x;
+		self.y = state.y;
+		self.slow_motion = state.slow_motion;
+		self.end_behavior = state.end_behavior;
+		self.opacity = state.opacity.clamp(0.0, 1.0);
+		self.blend_mode = BLEND_MODES.get(state.blend_mode).copied().unwrap_or(BLEND_MODES[0]);
+		self.track = state.track;
+		self.rate = if state.rate.is_finite() && state.rate > 0.0 { state.rate } else { 1.0 };
+		self.still_length = state.still_length.max(0.0);
+		self.scale_mode = state.scale_mode;
+		self.fade_in = state.fade_in.max(0.0);
+		self.fade_out = state.fade_out.max(0.0);
+		self.keyframes.clone_from(&state.keyframes);
+		self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+		let crop = state.crop.and_then(|(x, y, width, height)| Rect::from_xywh(x, y, width, height));
+		let stream = state.stream.min(self.source.video_streams - 1);
+		let grade = state.grade.clamped();
+		let size = (
+			NonZeroU32::new(state.size.0).unwrap_or(self.in_width),
+			NonZeroU32::new(state.size.1).unwrap_or(self.in_height)
+		);
+
+		let decoding_changed =
+			crop != self.crop ||
+			stream != self.stream ||
+			grade != self.grade ||
+			state.rotation % 4 != self.rotation ||
+			state.flip_horizontal != self.flip_horizontal ||
+			state.flip_vertical != self.flip_vertical ||
+			size != (self.in_width, self.in_height);
+
+		self.crop = crop;
+		self.stream = stream;
+		self.grade = grade;
+		self.rotation = state.rotation % 4;
+		self.flip_horizontal = state.flip_horizontal;
+		self.flip_vertical = state.flip_vertical;
+		(self.in_width, self.in_height) = size;
+
+		if decoding_changed {
+			self.refresh();
 		}
 	}
 
 	/// Multiplies the [`Video`]'s `in_width` and `in_height` fields by it's `scale` field, which is then set to None
-	///
-	/// It also advances the `iter` field by `1` frame and then `-1` frame in order to force the video to `reload()` and apply the new `width` and `height`
 	pub fn resize(&mut self) {
 		let (sx, sy) = self.scale.expect("Resized Video with no Scale");
 
-		self.in_width = NonZeroU16::new(((self.in_width.get() as f32 * sx).round() as u16).max(1)).unwrap();
-		self.in_height = NonZeroU16::new(((self.in_height.get() as f32 * sy).round() as u16).max(1)).unwrap();
+		self.in_width = NonZeroU32::new(((self.in_width.get() as f32 * sx).round() as u32).max(1)).unwrap();
+		self.in_height = NonZeroU32::new(((self.in_height.get() as f32 * sy).round() as u32).max(1)).unwrap();
+		self.animated_size = self.animated_size.map(|(width, height)| (width * sx, height * sy));
+
+		self.scale = None;
+
+		self.refresh();
+	}
+
+	/// Restores the [`Video`]'s native resolution from it's [`Source`], discarding any scaling
+	pub fn reset_size(&mut self) {
+		(self.in_width, self.in_height) = self.native_size();
 
 		self.scale = None;
 
-		self.load((self.frame_num + 1) as f32 / self.fps);
-		self.load((self.frame_num - 1) as f32 / self.fps);
+		self.refresh();
+	}
+
+	/// Sizes and positions the [`Video`] on a canvas according to it's `scale_mode`, if it has one
+	pub fn fit(&mut self, (width, height): (u32, u32)) {
+		let Some(mode) = self.scale_mode else {
+			return;
+		};
+
+		let (native_width, native_height) = self.native_size();
+		let native_width = native_width.get() as f32;
+		let native_height = native_height.get() as f32;
+
+		let scale_x = width as f32 / native_width;
+		let scale_y = height as f32 / native_height;
+
+		let (scale_x, scale_y) = match mode {
+			ScaleMode::Stretch => (scale_x, scale_y),
+			ScaleMode::Fit => (scale_x.min(scale_y), scale_x.min(scale_y)),
+			ScaleMode::Fill => (scale_x.max(scale_y), scale_x.max(scale_y))
+		};
+
+		let to_size = |size: f32| NonZeroU32::new(size.round().clamp(1.0, u32::MAX as f32) as u32).unwrap();
+
+		self.in_width = to_size(native_width * scale_x);
+		self.in_height = to_size(native_height * scale_y);
+
+		// Centered, so that bars or cut off edges are the same on both sides
+		self.x = (width as i32 - self.in_width.get() as i32) / 2;
+		self.y = (height as i32 - self.in_height.get() as i32) / 2;
+
+		self.scale = None;
+
+		self.refresh();
+	}
+
+	/// Shrinks the [`Video`] into a `corner` of a canvas as a picture-in-picture inset, fitting it within `scale` of the canvas' width and height
+	///
+	/// The inset is kept a small margin away from the canvas' edges, and sized freely from then on
+	pub fn inset(&mut self, (width, height): (u32, u32), corner: Corner, scale: f32) {
+		// Fraction of the canvas' shorter side left between the inset and the edges
+		const MARGIN: f32 = 0.03;
+
+		let (native_width, native_height) = self.native_size();
+		let native_width = native_width.get() as f32;
+		let native_height = native_height.get() as f32;
+
+		let factor = (width as f32 * scale / native_width).min(height as f32 * scale / native_height);
+		let to_size = |size: f32| NonZeroU32::new(size.round().clamp(1.0, u32::MAX as f32) as u32).unwrap();
+
+		self.in_width = to_size(native_width * factor);
+		self.in_height = to_size(native_height * factor);
+
+		let margin = (width.min(height) as f32 * MARGIN).round() as i32;
+
+		self.x = match corner {
+			Corner::TopLeft | Corner::BottomLeft => margin,
+			Corner::TopRight | Corner::BottomRight => width as i32 - self.in_width.get() as i32 - margin
+		};
+
+		self.y = match corner {
+			Corner::TopLeft | Corner::TopRight => margin,
+			Corner::BottomLeft | Corner::BottomRight => height as i32 - self.in_height.get() as i32 - margin
+		};
+
+		self.scale_mode = None;
+		self.scale = None;
+
+		self.refresh();
+	}
+
+	/// Returns the resolution of the [`Video`]'s source after cropping and rotating
+	pub fn native_size(&self) -> (NonZeroU32, NonZeroU32) {
+		let scale = |size: NonZeroU32, fraction: f32| NonZeroU32::new(((size.get() as f32 * fraction).round() as u32).max(1)).unwrap();
+
+		let (width, height) = self.crop.map_or((self.source.width, self.source.height), |crop| (
+			scale(self.source.width, crop.width()),
+			scale(self.source.height, crop.height())
+		));
+
+		if self.rotation % 2 == 0 {
+			(width, height)
+		} else {
+			(height, width)
+		}
+	}
+
+	/// Maps a rectangle from `0.0` to `1.0` of the [`Video`]'s rotated and flipped frame onto it's unrotated and unflipped one
+	fn unorient(&self, mut rect: Rect) -> Option<Rect> {
+		for _ in 0..self.rotation {
+			// Undoes a quarter turn clockwise
+			rect = Rect::from_xywh(rect.y(), 1.0 - rect.x() - rect.width(), rect.height(), rect.width())?;
+		}
+
+		Rect::from_xywh(
+			if self.flip_horizontal { 1.0 - rect.x() - rect.width() } else { rect.x() },
+			if self.flip_vertical { 1.0 - rect.y() - rect.height() } else { rect.y() },
+			rect.width(),
+			rect.height()
+		)
+	}
+
+	/// Crops the [`Video`] to the part of it's current frame which lies within `area`, leaving that part where it is on screen
+	///
+	/// Only the cropped region is decoded from then on
+	pub fn crop_to(&mut self, area: IntRect) {
+		let Some((width, height)) = self.size() else {
+			return;
+		};
+
+		let width = width as f32;
+		let height = height as f32;
+
+		let visible = Rect::from_ltrb(
+			((area.left() - self.x) as f32).max(0.0),
+			((area.top() - self.y) as f32).max(0.0),
+			((area.right() - self.x) as f32).min(width),
+			((area.bottom() - self.y) as f32).min(height)
+		).filter(|visible| visible.width() >= 1.0 && visible.height() >= 1.0);
+
+		let Some(visible) = visible else {
+			return;
+		};
+
+		// Crops are relative to the whole unrotated source, so they have to be combined with the current one
+		let Some(visible_crop) = Rect::from_xywh(visible.x() / width, visible.y() / height, visible.width() / width, visible.height() / height)
+			.and_then(|visible| self.unorient(visible)) else {
+			return;
+		};
+
+		let crop = self.crop.unwrap_or_else(|| Rect::from_xywh(0.0, 0.0, 1.0, 1.0).unwrap());
+
+		self.crop = Rect::from_xywh(
+			visible_crop.x().mul_add(crop.width(), crop.x()),
+			visible_crop.y().mul_add(crop.height(), crop.y()),
+			visible_crop.width() * crop.width(),
+			visible_crop.height() * crop.height()
+		);
+
+		self.x += visible.x() as i32;
+		self.y += visible.y() as i32;
+
+		self.in_width = NonZeroU32::new((visible.width().round() as u32).max(1)).unwrap();
+		self.in_height = NonZeroU32::new((visible.height().round() as u32).max(1)).unwrap();
+
+		self.refresh();
+	}
+
+	/// Removes the [`Video`]'s crop and restores it's native resolution
+	pub fn uncrop(&mut self) {
+		self.crop = None;
+		self.reset_size();
+	}
+
+	/// Rotates the [`Video`] a quarter turn clockwise around it's top left corner
+	pub fn rotate(&mut self) {
+		self.rotation = (self.rotation + 1) % 4;
+
+		(self.in_width, self.in_height) = (self.in_height, self.in_width);
+
+		self.refresh();
+	}
+
+	/// Mirrors the [`Video`] left to right as it's shown on screen
+	pub fn flip_horizontally(&mut self) {
+		// Flips are applied before rotating, so a sideways video has to be flipped along the other axis
+		if self.rotation % 2 == 0 {
+			self.flip_horizontal = !self.flip_horizontal;
+		} else {
+			self.flip_vertical = !self.flip_vertical;
+		}
+
+		self.refresh();
+	}
+
+	/// Mirrors the [`Video`] top to bottom as it's shown on screen
+	pub fn flip_vertically(&mut self) {
+		if self.rotation % 2 == 0 {
+			self.flip_vertical = !self.flip_vertical;
+		} else {
+			self.flip_horizontal = !self.flip_horizontal;
+		}
+
+		self.refresh();
+	}
+
+	/// Creates the `FFmpeg` filtergraph applied to the [`Video`]'s source when decoding, if any
+	fn filter(&self) -> Option<String> {
+		let crop = self.crop.map(|crop| format!(
+			"crop=w=iw*{}:h=ih*{}:x=iw*{}:y=ih*{}",
+			crop.width(),
+			crop.height(),
+			crop.x(),
+			crop.y()
+		));
+
+		let filters: Vec<_> = crop.into_iter()
+			.chain(self.flip_horizontal.then(|| String::from("hflip")))
+			.chain(self.flip_vertical.then(|| String::from("vflip")))
+			.chain(match self.rotation {
+				1 => Some(String::from("transpose=clock")),
+				2 => Some(String::from("transpose=clock,transpose=clock")),
+				3 => Some(String::from("transpose=cclock")),
+				_ => None
+			})
+			.chain(self.grade.filter())
+			.collect();
+
+		(!filters.is_empty()).then(|| filters.join(","))
+	}
+
+	/// Returns the size the [`Video`]'s current frame is shown at, which is larger than the frame itself when decoding at a lower [`DecodeQuality`]
+	pub fn size(&self) -> Option<(u32, u32)> {
+		self.frame.as_ref().map(|_| {
+			let (width, height) = self.display_size();
+			(width.round().max(1.0) as u32, height.round().max(1.0) as u32)
+		})
+	}
+
+	/// Returns the size the [`Video`] is displayed at, which it's `keyframes` may animate away from the decoded size
+	fn display_size(&self) -> (f32, f32) {
+		self.animated_size.unwrap_or_else(|| (self.in_width.get() as f32, self.in_height.get() as f32))
+	}
+
+	/// Moves and sizes the [`Video`] to where it's `keyframes` place it at a timestamp
+	fn animate(&mut self, timestamp: f32) {
+		let (Some(&first), Some(&last)) = (self.keyframes.first(), self.keyframes.last()) else {
+			self.animated_size = None;
+			return;
+		};
+
+		let time = timestamp - self.duration.start();
+		let i = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+
+		let keyframe = match i {
+			0 => first,
+			i if i == self.keyframes.len() => last,
+			i => self.keyframes[i - 1].lerp(self.keyframes[i], time)
+		};
+
+		self.x = keyframe.x.round() as i32;
+		self.y = keyframe.y.round() as i32;
+		self.animated_size = Some((keyframe.width, keyframe.height));
+	}
+
+	/// Records the current position and display size of the [`Video`] as a [`Keyframe`] at a timestamp, replacing any already there
+	///
+	/// The display size is recorded without any `scale` still being applied, which is drawn on top of it until the [`Video`] is resized
+	pub fn add_keyframe(&mut self, timestamp: f32) {
+		let (width, height) = self.display_size();
+
+		let keyframe = Keyframe {
+			time: timestamp - self.duration.start(),
+			x: self.x as f32,
+			y: self.y as f32,
+			width,
+			height
+		};
+
+		let i = self.keyframes.partition_point(|other| other.time < keyframe.time);
+
+		if self.keyframes.get(i).is_some_and(|other| (other.time - keyframe.time).abs() < f32::EPSILON) {
+			self.keyframes[i] = keyframe;
+		} else {
+			self.keyframes.insert(i, keyframe);
+		}
+
+		self.animated_size = Some((keyframe.width, keyframe.height));
+	}
+
+	/// Records a position or display size set by hand in the [`Keyframe`] at a timestamp if the [`Video`] is animated, so that the next frame doesn't move it back
+	pub fn update_keyframe(&mut self, timestamp: f32) {
+		if !self.keyframes.is_empty() {
+			self.add_keyframe(timestamp);
+		}
+	}
+
+	/// Removes every [`Keyframe`], leaving the [`Video`] where and at the size they last placed it
+	pub fn clear_keyframes(&mut self) {
+		self.keyframes.clear();
+
+		if let Some((width, height)) = self.animated_size.take() {
+			self.in_width = NonZeroU32::new((width.round() as u32).max(1)).unwrap();
+			self.in_height = NonZeroU32::new((height.round() as u32).max(1)).unwrap();
+
+			self.refresh();
+		}
+	}
+
+	/// Returns how much the [`Video`]'s current frame is scaled when drawn, to it's display size and by it's `scale` field, unless it isn't scaled at all
+	pub fn draw_scale(&self) -> Option<(f32, f32)> {
+		let frame = self.frame.as_ref()?;
+		let (sx, sy) = self.scale.unwrap_or((1.0, 1.0));
+
+		let (width, height) = self.display_size();
+
+		let sx = sx * width / frame.width() as f32;
+		let sy = sy * height / frame.height() as f32;
+
+		((sx - 1.0).abs() > f32::EPSILON || (sy - 1.0).abs() > f32::EPSILON).then_some((sx, sy))
+	}
+
+	/// Sets the speed the project is playing at, or `None` while paused
+	///
+	/// While playing, `FFmpeg` only reads the source a bit faster than it's shown instead of decoding ahead as fast as it can, which is applied the next time the [`Video`] seeks
+	///
+	/// While paused, `FFmpeg` isn't limited, so that scrubbing forward stays fast, and instead stops once the frames decoded ahead fill their buffer
+	pub fn set_playback_speed(&mut self, speed: Option<f32>) {
+		self.playback_speed = speed.map(f32::abs);
+	}
+
+	/// Limits the number of threads `FFmpeg` decodes with, which is applied the next time the [`Video`] seeks
+	pub fn set_threads(&mut self, threads: usize) {
+		self.threads = Some(threads.max(1));
+	}
+
+	/// Sets the [`SeekMode`] used from the next seek on
+	///
+	/// Switching to [`SeekMode::Exact`] after a fast seek decodes the current frame again, so that the frame shown once scrubbing stops is the right one
+	pub fn set_seek_mode(&mut self, mode: SeekMode) {
+		if mode != self.seek_mode {
+			self.seek_mode = mode;
+
+			if mode == SeekMode::Exact && self.approximate {
+				self.suspend();
+				self.shown_num = u32::MAX;
+			}
+		}
+	}
+
+	/// Sets how much larger than it's display size the [`Video`] is rendered, reloading it if that changed
+	pub fn set_render_scale(&mut self, scale: f32) {
+		if (scale - self.render_scale).abs() > f32::EPSILON {
+			self.render_scale = scale;
+			self.refresh();
+		}
+	}
+
+	/// Sets the [`DecodeQuality`], reloading the [`Video`] if it changed
+	pub fn set_quality(&mut self, quality: DecodeQuality) {
+		if quality != self.quality {
+			self.quality = quality;
+			self.refresh();
+		}
+	}
+
+	/// Returns whether a point lies within the [`Video`]'s current frame
+	pub fn contains(&self, x: i32, y: i32) -> bool {
+		self.size().is_some_and(|(width, height)| {
+			let half_width = width as i32 / 2;
+			let half_height = height as i32 / 2;
+
+			(self.x + half_width - x).abs() < half_width && (self.y + half_height - y).abs() < half_height
+		})
+	}
+
+	/// Restarts decoding at the frame being shown with `reload()`, applying a new size, crop, rotation, flip, grade, stream or quality
+	///
+	/// Without a frame shown, such as before the clip starts, `FFmpeg` is instead restarted at whichever frame the next [`load`](Self::load) maps the playhead to
+	///
+	/// The `cache` is cleared, since it's frames were decoded with the old settings
+	fn refresh(&mut self) {
+		self.cache.clear();
+
+		if self.error.is_some() {
+			return; // Keep showing the placeholder
+		}
+
+		if self.shown_num == u32::MAX {
+			self.suspend();
+			return;
+		}
+
+		let num = self.shown_num;
+		self.frame_num = num;
+
+		self.suspended = false;
+		self.reload();
+
+		if self.error.is_none() {
+			if let Ok(frame) = self.iter.recv() {
+				self.show(num, frame);
+				self.shown_num = num;
+			}
+		}
+	}
+
+	/// Returns the [`Video`]'s color adjustments
+	pub const fn grade(&self) -> Grade {
+		self.grade
+	}
+
+	/// Changes the [`Video`]'s color adjustments, decoding it again if they changed
+	pub fn set_grade(&mut self, grade: Grade) {
+		let grade = grade.clamped();
+
+		if grade != self.grade {
+			self.grade = grade;
+			self.refresh();
+		}
+	}
+
+	/// Switches to decoding the source's next video stream, wrapping around to the first
+	pub fn next_stream(&mut self) {
+		self.stream = (self.stream + 1) % self.source.video_streams;
+
+		self.refresh();
+	}
+
+	/// Switches to the next blend mode in [`BLEND_MODES`]
+	pub fn next_blend_mode(&mut self) {
+		let i = BLEND_MODES.iter().position(|&mode| mode == self.blend_mode).map_or(0, |i| (i + 1) % BLEND_MODES.len());
+		self.blend_mode = BLEND_MODES[i];
+	}
+
+	/// Returns how much the [`Video`] has faded in or out at a timestamp, from `0.0` when transparent to `1.0` when fully visible
+	pub fn fade_at(&self, timestamp: f32) -> f32 {
+		let fade_in = if self.fade_in > 0.0 { (timestamp - self.duration.start()) / self.fade_in } else { 1.0 };
+		let fade_out = if self.fade_out > 0.0 { (self.end() - timestamp) / self.fade_out } else { 1.0 };
+
+		fade_in.min(fade_out).clamp(0.0, 1.0)
+	}
+
+	/// Returns the opacity the [`Video`] is drawn with at the last loaded timestamp, which is lowered while fading in or out
+	pub fn alpha(&self) -> f32 {
+		self.opacity * self.fade
+	}
+
+	/// Returns the blend mode the [`Video`] is drawn with, which blends translucent [`Video`]s over the ones beneath them rather than replacing them
+	pub fn effective_blend_mode(&self) -> BlendMode {
+		if self.blend_mode == BlendMode::Source && self.alpha() < 1.0 {
+			BlendMode::SourceOver
+		} else {
+			self.blend_mode
+		}
+	}
+
+	/// Returns whether the [`Video`] completely hides whatever is beneath it, which is only the case when it replaces it opaquely
+	///
+	/// Blending over what's beneath, as by default, lets it show through any transparent parts of the source
+	pub fn is_opaque(&self) -> bool {
+		self.blend_mode == BlendMode::Source && self.alpha() >= 1.0
+	}
+
+	/// Returns whether the [`Video`] is on the timeline at a timestamp
+	pub fn is_active(&self, timestamp: f32) -> bool {
+		timestamp >= *self.duration.start() && timestamp <= self.end()
+	}
+
+	/// Returns the time at which the [`Video`] ends, using the length of it's source if it hasn't been played that far yet
+	pub fn end(&self) -> f32 {
+		let end = self.cut_short.unwrap_or_else(|| self.source_end().map_or_else(|| *self.duration.end(), |end| self.duration.end().max(end)));
+
+		self.trim_end.map_or(end, |trim_end| end.min(trim_end)).max(*self.duration.start())
+	}
+
+	/// Returns whether the [`Video`] keeps going after it's source runs out, by looping or holding it's last frame, without being cut off
+	pub fn is_endless(&self) -> bool {
+		self.end_behavior != EndBehavior::Black && self.trim_end.is_none()
+	}
+
+	/// Returns the time on the timeline at which the [`Video`]'s source runs out, if known
+	pub fn source_end(&self) -> Option<f32> {
+		self.length().map(|length| self.shift + length)
+	}
+
+	/// Moves the [`Video`] along the timeline so that it starts at `start`, along with it's content
+	pub fn move_to(&mut self, start: f32) {
+		let offset = start - self.duration.start();
+
+		self.duration = start..=self.duration.end() + offset;
+		self.shift += offset;
+		self.trim_end = self.trim_end.map(|end| end + offset);
+	}
+
+	/// Moves the start of the [`Video`] without moving it's content, so that less or more of the beginning of it's source is shown
+	///
+	/// The start can't be moved before the beginning of the source or past the end of the [`Video`]
+	pub fn trim_head(&mut self, start: f32) {
+		let start = start.max(self.shift).min(self.end());
+		self.duration = start..=self.duration.end().max(start);
+	}
+
+	/// Moves the end of the [`Video`], cutting it off early or showing more of it's source
+	///
+	/// The end can't be moved before the start of the [`Video`], or past the end of the source unless it loops or holds it's last frame
+	pub fn trim_tail(&mut self, end: f32) {
+		let end = match (self.end_behavior, self.source_end()) {
+			(EndBehavior::Black, Some(source_end)) => end.min(source_end),
+			_ => end
+		}.max(*self.duration.start());
+
+		self.duration = *self.duration.start()..=end;
+		self.trim_end = Some(end);
+	}
+
+	/// Cuts the [`Video`] off at a timestamp, returning the [`VideoState`] of a [`Video`] continuing from there, unless the timestamp is outside of it
+	pub fn split(&mut self, timestamp: f32) -> Option<VideoState> {
+		if timestamp <= *self.duration.start() || timestamp >= self.end() {
+			return None;
+		}
+
+		let mut rest = self.state();
+		rest.start = timestamp;
+		rest.end = rest.end.max(timestamp);
+		// Keyframes are relative to the start of the clip
+		rest.keyframes = self.keyframes.iter()
+			.filter_map(|&keyframe| {
+				let time = keyframe.time - (timestamp - self.duration.start());
+				(time >= 0.0).then_some(Keyframe { time, ..keyframe })
+			})
+			.collect();
+		rest.fade_in = 0.0;
+
+		self.duration = *self.duration.start()..=timestamp;
+		self.trim_end = Some(timestamp);
+		self.fade_out = 0.0;
+
+		Some(rest)
+	}
+
+	/// Returns the length of the [`Video`]'s source in seconds of playback, if known
+	///
+	/// Images last for their `still_length`
+	pub fn length(&self) -> Option<f32> {
+		if self.source.is_image {
+			return Some(self.still_length);
+		}
+
+		self.source.length.map(|length| length / self.speed())
+	}
+
+	/// Returns the time in the [`Video`]'s source which is shown at a timestamp
+	///
+	/// Looping only changes which part of the source is shown, the `duration` keeps growing with `timestamp`
+	pub fn source_time(&self, timestamp: f32) -> f32 {
+		let time = match (self.end_behavior, self.length()) {
+			(EndBehavior::Loop, Some(length)) if length > 0.0 => (timestamp - self.shift).rem_euclid(length),
+			_ => timestamp - self.shift
+		};
+
+		time * self.speed()
+	}
+
+	/// Returns how fast the [`Video`]'s source plays relative to real time
+	pub fn speed(&self) -> f32 {
+		self.fps() / self.source.fps
+	}
+
+	/// Returns the number of source frames shown per second of playback
+	pub fn fps(&self) -> f32 {
+		let fps = if self.slow_motion {
+			self.source.fps.min(SLOW_MOTION_FPS)
+		} else {
+			self.source.fps
+		};
+
+		fps * self.rate
+	}
+
+	/// Quits the [`Video`]'s `FFmpeg` instance to make room for other [`Video`]s to decode, keeping the current frame until it's loaded again
+	pub fn suspend(&mut self) {
+		if !self.suspended {
+			drop(self.ffmpeg.quit());
+			self.suspended = true;
+		}
 	}
 
 	/// Replaces the [`Video`]'s `ffmpeg` and `iter` fields with new ones starting from `Video.timestamp`
 	///
-	/// This also applies changes from the `in_width` and `in_height` fields
+	/// This also applies changes from the `in_width`, `in_height`, `crop`, `rotation` and flip fields
+	///
+	/// If `FFmpeg` fails, the [`Video`] is marked with an `error` and shows a placeholder from then on
 	fn reload(&mut self) {
 		drop(self.ffmpeg.quit()); // Probably not good but .unwrap() sometimes panics
 
-		self.ffmpeg = FfmpegCommand::new()
+		let ffmpeg = spawn_decoder(
+			&self.source.path,
+			self.stream,
+			self.hwaccel,
+			Some(self.source.pix_fmt_at(self.decode_size())),
+			Some((frame_time(self.frame_num, self.source.fps), self.seek_mode)).filter(|_| !self.source.is_live),
+			self.playback_speed.map(|speed| (speed * self.speed() * READRATE_HEADROOM).max(f32::EPSILON)),
+			Some(self.decode_size()),
+			self.filter().as_deref(),
+			self.threads
+		);
+
+		self.approximate = self.seek_mode == SeekMode::Fast && self.frame_num > 0 && !self.source.is_live;
+
+		match ffmpeg {
+			Ok(ffmpeg) => self.ffmpeg = ffmpeg,
+			Err(error) => return self.fail(error)
+		}
+
+		match self.ffmpeg.iter() {
+			Ok(iter) => self.iter = frames(iter, &self.source.path, self.ffmpeg_error.clone(), self.decode_size()),
+			Err(error) => self.fail(VideoError::Ffmpeg(error.to_string()))
+		}
+	}
+
+	/// Returns the resolution frames are decoded at, which is the display resolution scaled to the size it's rendered at and divided according to the [`DecodeQuality`]
+	fn decode_size(&self) -> (u32, u32) {
+		let divisor = self.quality.divisor();
+		let scale = |size: NonZeroU32| (size.get() as f32 * self.render_scale).round() as u32;
+
+		(
+			(scale(self.in_width) / divisor).max(1),
+			(scale(self.in_height) / divisor).max(1)
+		)
+	}
+
+	/// Takes the last error reported by `FFmpeg`, if any
+	fn take_ffmpeg_error(&self) -> Option<String> {
+		self.ffmpeg_error.lock().unwrap().take()
+	}
+
+	/// Marks the [`Video`] as failed, replacing it's frame with a placeholder of the same size
+	fn fail(&mut self, error: VideoError) {
+		self.frame = self.placeholder();
+		self.error = Some(error);
+	}
+
+	/// Creates a warning colored frame of the [`Video`]'s size, shown in place of frames that failed to decode
+	fn placeholder(&self) -> Option<Arc<Pixmap>> {
+		Pixmap::new(self.in_width.get(), self.in_height.get()).map(|mut placeholder| {
+			placeholder.fill(Color::from_rgba8(120, 30, 40, 255));
+			Arc::new(placeholder)
+		})
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	/// Generates a `seconds` long `testsrc` clip at `fps` in the temporary directory, or returns `None` so that the test is skipped if `FFmpeg` isn't installed
+	pub fn test_clip(name: &str, seconds: u32, fps: u32) -> Option<PathBuf> {
+		generate(name, seconds, fps, None)
+	}
+
+	/// Generates a `testsrc` clip like [`test_clip`], with a filter applied to it
+	fn generate(name: &str, seconds: u32, fps: u32, filter: Option<&str>) -> Option<PathBuf> {
+		if !ffmpeg_sidecar::command::ffmpeg_is_installed() {
+			eprintln!("FFmpeg isn't installed, skipping");
+			return None;
+		}
+
+		let path = std::env::temp_dir().join(format!("dusk-{name}-{}.mkv", std::process::id()));
+
+		let mut command = ffmpeg::command();
+
+		command
 			.hide_banner()
-			.create_no_window()
-			.no_audio()
-			.args(["-sn", "-dn"])
-			.hwaccel("auto")
-			.seek((self.frame_num as f32 / self.fps).to_string())
-			.input(self.path.to_str().unwrap())
-			.format("rawvideo")
-			.pix_fmt("rgba")
-			.size(self.in_width.get() as u32, self.in_height.get() as u32)
-			.no_overwrite()
-			.pipe_stdout()
-			.spawn().unwrap();
-
-		self.iter = Box::new(self.ffmpeg.iter().unwrap().filter_frames());
+			.format("lavfi")
+			.input(format!("testsrc=duration={seconds}:size=320x240:rate={fps}"));
+
+		if let Some(filter) = filter {
+			command.filter(filter);
+		}
+
+		let mut ffmpeg = command
+			.overwrite()
+			.output(path.to_string_lossy())
+			.spawn()
+			.unwrap();
+
+		ffmpeg.iter().unwrap().for_each(drop);
+		assert!(ffmpeg.wait().unwrap().success(), "FFmpeg couldn't generate {}", path.display());
+
+		Some(path)
+	}
+
+	/// Returns the color of the only pixel of a [`Pixmap`], with it's alpha premultiplied
+	fn pixel(pixmap: &Pixmap) -> (u8, u8, u8, u8) {
+		let pixel = pixmap.pixel(0, 0).unwrap();
+
+		(pixel.red(), pixel.green(), pixel.blue(), pixel.alpha())
+	}
+
+	#[test]
+	fn native_pixel_formats_are_converted_to_rgba() {
+		let frame = |pix_fmt: &str, width, height, data| to_pixmap(OutputVideoFrame {
+			width,
+			height,
+			pix_fmt: pix_fmt.to_owned(),
+			output_index: 0,
+			data,
+			frame_num: 0,
+			timestamp: 0.0
+		}).unwrap();
+
+		assert_eq!(pixel(&frame("bgra", 1, 1, vec![0, 128, 255, 255])), (255, 128, 0, 255));
+		assert_eq!(pixel(&frame("rgb24", 1, 1, vec![255, 128, 0])), (255, 128, 0, 255));
+
+		// Limited range white, black and pure red, each spread over a whole 2x2 block sharing it's chroma
+		assert_eq!(pixel(&frame("yuv420p", 2, 2, vec![235, 235, 235, 235, 128, 128])), (255, 255, 255, 255));
+		assert_eq!(pixel(&frame("yuv420p", 2, 2, vec![16, 16, 16, 16, 128, 128])), (0, 0, 0, 255));
+
+		assert_eq!(pixel(&frame("yuv420p", 2, 2, vec![81, 81, 81, 81, 90, 240])), (255, 0, 0, 255));
+	}
+
+	#[test]
+	fn half_transparent_frames_blend_over_opaque_ones() {
+		// `FFmpeg` outputs straight alpha, so this is white at half opacity
+		let frame = to_pixmap(OutputVideoFrame {
+			width: 1,
+			height: 1,
+			pix_fmt: "rgba".to_owned(),
+			output_index: 0,
+			data: vec![255, 255, 255, 128],
+			frame_num: 0,
+			timestamp: 0.0
+		}).unwrap();
+
+		assert_eq!(pixel(&frame), (128, 128, 128, 128), "Frames are premultiplied");
+
+		let draw = |blend_mode| {
+			let mut pixmap = Pixmap::new(1, 1).unwrap();
+			pixmap.fill(Color::from_rgba8(255, 0, 0, 255));
+			pixmap.draw_pixmap(0, 0, frame.as_ref(), &tiny_skia::PixmapPaint { blend_mode, ..Default::default() }, tiny_skia::Transform::identity(), None);
+
+			pixel(&pixmap)
+		};
+
+		// Blending over by default lets the red beneath show through
+		let (red, green, blue, alpha) = draw(BLEND_MODES[0]);
+		assert_eq!((red, alpha), (255, 255));
+		assert!(green.abs_diff(128) <= 1 && blue.abs_diff(128) <= 1);
+
+		// Replacing what's beneath leaves the frame's own transparency
+		assert_eq!(draw(BlendMode::Source), (128, 128, 128, 128));
+	}
+
+	#[test]
+	fn frames_round_trip_at_common_rates() {
+		for fps in [24.0, 29.97, 60.0, 120.0] {
+			// An hour of frames, far enough for rounding errors to show up
+			for num in 0..(fps * 3600.0) as u32 {
+				assert_eq!(frame_at(frame_time(num, fps), fps), num, "Frame {num} at {fps} FPS");
+			}
+		}
+	}
+
+	#[test]
+	fn decoded_frames_are_counted_and_timed() {
+		for fps in [24, 60] {
+			let Some(path) = test_clip(&format!("decode-{fps}"), 2, fps) else { return };
+			let Ok(mut ffmpeg) = spawn_decoder(&path, 0, "none", Some("rgba"), None, None, None, None, None) else { panic!("Couldn't decode {}", path.display()) };
+
+			let frames: Vec<_> = ffmpeg.iter().unwrap().filter_frames().collect();
+			drop(ffmpeg.wait());
+
+			assert_eq!(frames.len(), 2 * fps as usize);
+
+			for (num, frame) in (0..).zip(&frames) {
+				assert_eq!(frame.frame_num, num);
+				assert_eq!(frame_at(frame.timestamp, fps as f32), num, "Frame {num} at {fps} FPS is timed at {}", frame.timestamp);
+				assert!((frame.timestamp - frame_time(num, fps as f32)).abs() < 0.5 / fps as f32);
+			}
+
+			// The `Source` a `Video` reads agrees with what was decoded
+			let Ok(mut video) = Video::new(path.clone(), 0.0, &mut Sources::default()) else { panic!("Couldn't open {}", path.display()) };
+
+			assert!((video.source.fps - fps as f32).abs() < 0.01);
+			assert_eq!((video.source.width.get(), video.source.height.get()), (320, 240));
+
+			// Loading one frame further each time decodes exactly that frame
+			for num in 0..fps {
+				video.load(frame_time(num, fps as f32));
+
+				assert_eq!(video.frame_num, num);
+				assert_eq!(video.shown_num, num);
+			}
+
+			drop(video.ffmpeg.quit());
+			drop(std::fs::remove_file(&path));
+		}
+	}
+
+	#[test]
+	fn variable_frame_rate_sources_decode_at_a_constant_rate() {
+		// Ten frames, a second long gap, then ten more, which Matroska keeps as variable frame rate
+		let Some(path) = generate("vfr", 2, 10, Some("setpts=N/10/TB+gte(N\\,10)/TB")) else { return };
+		let Ok(mut ffmpeg) = spawn_decoder(&path, 0, "none", Some("rgba"), None, None, None, None, None) else { panic!("Couldn't decode {}", path.display()) };
+
+		let frames: Vec<_> = ffmpeg.iter().unwrap().filter_frames().collect();
+		drop(ffmpeg.wait());
+		drop(std::fs::remove_file(&path));
+
+		// The gap is filled by repeating the frame before it, so frame numbers stay in step with presentation timestamps
+		assert_eq!(frames.len(), 30);
+		assert!(frames[10..20].iter().all(|frame| frame.data == frames[9].data));
+		assert_ne!(frames[20].data, frames[9].data);
+	}
+
+	#[test]
+	fn scrubbing_past_the_end_ends_the_clip() {
+		let Some(path) = test_clip("end", 1, 30) else { return };
+		let Ok(mut video) = Video::new(path.clone(), 0.0, &mut Sources::default()) else { panic!("Couldn't open {}", path.display()) };
+
+		video.load(0.5);
+		assert!(video.frame.is_some());
+
+		video.load(2.0);
+		assert!(video.error.is_none());
+		assert!(video.frame.is_none(), "Clips show nothing after their source runs out");
+		assert!(video.cut_short.is_none(), "Running out at the detected length isn't ending early");
+
+		// Scrubbing back shows frames again
+		video.load(0.5);
+		assert!(video.frame.is_some());
+
+		drop(video.ffmpeg.quit());
+		drop(std::fs::remove_file(path));
+	}
+
+	#[test]
+	fn high_frame_rate_sources_play_in_real_time_or_slow_motion() {
+		let Some(path) = test_clip("120-fps", 1, 120) else { return };
+
+		for slow_motion in [true, false] {
+			let Ok(mut video) = Video::new(path.clone(), 0.0, &mut Sources::default()) else { panic!("Couldn't open {}", path.display()) };
+			video.slow_motion = slow_motion;
+
+			// Slow motion shows every frame at 30 FPS, stretching the second of footage over four, while real time skips to every fourth frame
+			let (length, step) = if slow_motion { (4, 1) } else { (1, 4) };
+			assert!(video.length().is_some_and(|actual| (actual - length as f32).abs() < 0.01));
+
+			for num in 0..length * 30 {
+				video.load(num as f32 / 30.0);
+
+				assert!(video.frame.is_some());
+				assert_eq!(video.shown_num, num * step, "Step {num} of {} playback", if slow_motion { "slow motion" } else { "real time" });
+			}
+
+			drop(video.ffmpeg.quit());
+		}
+
+		drop(std::fs::remove_file(path));
 	}
 }
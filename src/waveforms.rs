@@ -0,0 +1,60 @@
+use std::{collections::HashMap, io::Read, path::{Path, PathBuf}, sync::{Arc, OnceLock}, thread};
+
+use crate::{audio, source::Source};
+
+/// Number of peaks computed per second of a [`Source`]'s audio, which is finer than the timeline is drawn at
+pub const PEAKS_PER_SECOND: u32 = 50;
+
+/// Sample rate audio is decoded at for waveforms, which only need to be accurate enough to show loudness
+const SAMPLE_RATE: u32 = 8000;
+
+/// Lowest and highest sample of each [`PEAKS_PER_SECOND`] slice of audio
+type Peaks = Vec<(f32, f32)>;
+
+/// Decodes a file's audio down to mono, returning it's [`Peaks`]
+fn decode(path: &Path, length: f32) -> Peaks {
+	let Some(mut ffmpeg) = audio::spawn_decoder(path, 0.0, Some(length), (SAMPLE_RATE, 1), 1.0, false) else { return Vec::new() };
+
+	let mut peaks = Vec::new();
+
+	if let Some(mut stdout) = ffmpeg.take_stdout() {
+		let mut bytes = vec![0; (SAMPLE_RATE / PEAKS_PER_SECOND) as usize * 4];
+
+		// The last partial slice is left out
+		while stdout.read_exact(&mut bytes).is_ok() {
+			peaks.push(audio::to_samples(&bytes).fold((0.0, 0.0), |(min, max): (f32, f32), sample| (min.min(sample), max.max(sample))));
+		}
+	}
+
+	drop(ffmpeg.wait());
+
+	peaks
+}
+
+/// Waveforms of the audio of [`Source`]s, keyed by path
+///
+/// Each waveform is generated on it's own thread the first time it's requested
+#[derive(Default)]
+pub struct Waveforms(HashMap<PathBuf, Arc<OnceLock<Peaks>>>);
+
+impl Waveforms {
+	/// Returns the [`Peaks`] of a [`Source`]'s audio, or `None` while they're still being generated
+	///
+	/// Sources without audio or of unknown length have no waveform
+	pub fn get(&mut self, source: &Source) -> Option<&[(f32, f32)]> {
+		let length = source.length.filter(|_| source.has_audio)?;
+
+		let waveform = self.0.entry(source.path.clone()).or_insert_with(|| {
+			let waveform = Arc::<OnceLock<_>>::default();
+
+			let path = source.path.clone();
+			let done = waveform.clone();
+
+			thread::spawn(move || drop(done.set(decode(&path, length))));
+
+			waveform
+		});
+
+		waveform.get().map(Vec::as_slice)
+	}
+}